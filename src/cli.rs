@@ -25,6 +25,11 @@ struct Cli {
     /// Do not save any file.
     #[arg(long = "dry-run")]
     dry_run: bool,
+    /// Report every error instead of stopping at the first one.
+    ///
+    /// The objects that could still be drawn are saved.
+    #[arg(long = "keep-going", short = 'k')]
+    keep_going: bool,
 }
 
 macro_rules! ok_or_print {
@@ -35,6 +40,15 @@ macro_rules! ok_or_print {
             return Ok(());
         }
     };
+    ($res:expr, $str:literal, keep going if $keep_going:expr) => {
+        let result = $res;
+        if let Err(e) = result {
+            println!($str, e);
+            if !$keep_going {
+                return Ok(());
+            }
+        }
+    };
 }
 
 pub fn cli_main() -> Result<()> {
@@ -46,9 +60,13 @@ pub fn cli_main() -> Result<()> {
             Some(path) => path,
             None => input.with_extension("svg"),
         };
-        let file = fs::read_to_string(input)?;
         let mut interpreter = InterpreterState::new();
-        ok_or_print!(interpreter.interpret(&file), "Cannot interpret file: {}");
+        interpreter.set_recover(cli.keep_going);
+        ok_or_print!(
+            interpreter.interpret_file(&input),
+            "Cannot interpret file: {}",
+            keep going if cli.keep_going
+        );
         if !cli.dry_run {
             ok_or_print!(
                 fs::write(output, interpreter.emit()?),
@@ -59,13 +77,17 @@ pub fn cli_main() -> Result<()> {
         env::set_current_dir(&input)?;
         let entries = fs::read_dir(".")?;
         let mut interpreter = InterpreterState::new();
+        interpreter.set_recover(cli.keep_going);
         for entry in entries {
             let input = entry?.path();
             if let Some(ext) = input.extension() {
                 if ext == cli.extension.as_str() {
                     let output = input.with_extension("svg");
-                    let file = fs::read_to_string(input)?;
-                    ok_or_print!(interpreter.interpret(&file), "Cannot interpret file: {}");
+                    ok_or_print!(
+                        interpreter.interpret_file(&input),
+                        "Cannot interpret file: {}",
+                        keep going if cli.keep_going
+                    );
                     if !cli.dry_run {
                         ok_or_print!(
                             fs::write(output, interpreter.emit()?),
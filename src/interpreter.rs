@@ -1,8 +1,13 @@
 mod builtin;
+pub mod diagnostic;
 mod draw;
 mod eval;
+mod graph;
 pub mod interpret;
+mod loops;
+mod macros;
 mod parser;
+mod select;
 mod structs;
 mod utils;
 
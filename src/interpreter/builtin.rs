@@ -1,3 +1,10 @@
+pub mod apollonius;
+pub mod centers;
+pub mod circles;
 pub mod config;
 pub mod decor;
 pub mod functions;
+pub mod predicates;
+pub mod projective;
+pub mod transforms;
+pub mod triangles;
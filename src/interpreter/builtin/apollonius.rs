@@ -0,0 +1,210 @@
+// Circles tangent to three points, lines or circles, i.e. the problem of
+// Apollonius.
+//
+// A circle with center `(x, y)` and signed radius `r` is tangent to a circle
+// `(a, b, s)` when `(x - a)^2 + (y - b)^2 = (r - s)^2`, and to a line
+// `ax + by + c = 0` (with `a^2 + b^2 = 1`) when `ax + by + c = r`. Taking both
+// orientations of each line and circle, every tangent circle is a solution
+// with `r > 0` of one of the systems.
+#![allow(non_snake_case)]
+
+use super::predicates::TOLERANCE;
+use crate::interpreter::utils::{FuncError, GObject};
+use itertools::Itertools;
+use metric_rs::objects::{Circle, Point};
+
+type V3 = [f64; 3];
+
+const OBJECT: &str = "Point or Line or Circle";
+
+/// An oriented point, line or circle, as an equation
+/// `q(x, y, r) + e . (x, y, r) + f = 0`, where `q = x^2 + y^2 - r^2` for
+/// circles and `q = 0` for lines.
+#[derive(Clone, Copy)]
+struct Cycle {
+    quadratic: bool,
+    e: V3,
+    f: f64,
+}
+
+impl Cycle {
+    #[inline]
+    fn circle(a: f64, b: f64, s: f64) -> Self {
+        Cycle {
+            quadratic: true,
+            e: [-2.0 * a, -2.0 * b, 2.0 * s],
+            f: a * a + b * b - s * s,
+        }
+    }
+    /// Both orientations of an object, or `None` if it cannot be tangent to a
+    /// circle.
+    fn orientations(obj: &GObject) -> Option<Vec<Cycle>> {
+        match *obj {
+            GObject::Point(p) => Some(vec![Cycle::circle(p.x, p.y, 0.0)]),
+            GObject::Circle(c) => Some(vec![
+                Cycle::circle(c.O.x, c.O.y, c.r),
+                Cycle::circle(c.O.x, c.O.y, -c.r),
+            ]),
+            GObject::Line(l) => {
+                let norm = l.a.hypot(l.b);
+                let (a, b, c) = (l.a / norm, l.b / norm, l.c / norm);
+                Some(vec![
+                    Cycle {
+                        quadratic: false,
+                        e: [a, b, -1.0],
+                        f: c,
+                    },
+                    Cycle {
+                        quadratic: false,
+                        e: [-a, -b, -1.0],
+                        f: -c,
+                    },
+                ])
+            }
+            _ => None,
+        }
+    }
+    /// The linear equation `e . (x, y, r) + f = 0` obtained by subtracting the
+    /// equation of `base`, which has the same quadratic part.
+    #[inline]
+    fn minus(self, base: Cycle) -> (V3, f64) {
+        if self.quadratic {
+            (sum(&[(1.0, self.e), (-1.0, base.e)]), self.f - base.f)
+        } else {
+            (self.e, self.f)
+        }
+    }
+}
+
+#[inline]
+fn dot(u: V3, v: V3) -> f64 {
+    u[0] * v[0] + u[1] * v[1] + u[2] * v[2]
+}
+
+/// The quadratic form `x^2 + y^2 - r^2`, polarized.
+#[inline]
+fn form(u: V3, v: V3) -> f64 {
+    u[0] * v[0] + u[1] * v[1] - u[2] * v[2]
+}
+
+#[inline]
+fn cross(u: V3, v: V3) -> V3 {
+    [
+        u[1] * v[2] - u[2] * v[1],
+        u[2] * v[0] - u[0] * v[2],
+        u[0] * v[1] - u[1] * v[0],
+    ]
+}
+
+/// A linear combination of vectors.
+#[inline]
+fn sum(terms: &[(f64, V3)]) -> V3 {
+    let mut v = [0.0; 3];
+    for (k, u) in terms {
+        v.iter_mut().zip(u).for_each(|(x, y)| *x += k * y);
+    }
+    v
+}
+
+/// Solutions `(x, y, r)` of the system of three oriented objects.
+fn solve(cycles: [Cycle; 3]) -> Vec<V3> {
+    let Some(k) = cycles.iter().position(|c| c.quadratic) else {
+        // Three lines, so the system is linear.
+        let [(e1, f1), (e2, f2), (e3, f3)] = cycles.map(|c| (c.e, c.f));
+        let det = dot(e1, cross(e2, e3));
+        if det.abs() <= TOLERANCE {
+            return Vec::new();
+        }
+        let terms = [
+            (-f1 / det, cross(e2, e3)),
+            (-f2 / det, cross(e3, e1)),
+            (-f3 / det, cross(e1, e2)),
+        ];
+        return vec![sum(&terms)];
+    };
+    let base = cycles[k];
+    let mut others = (0..3).filter(|&i| i != k).map(|i| cycles[i].minus(base));
+    let ((e1, f1), (e2, f2)) = (others.next().unwrap(), others.next().unwrap());
+    // The two linear equations give a line `p + td`, which is intersected with
+    // the quadric of `base`.
+    let d = cross(e1, e2);
+    let norm = dot(d, d);
+    if norm <= TOLERANCE * TOLERANCE {
+        return Vec::new();
+    }
+    let p = sum(&[(-f1 / norm, cross(e2, d)), (-f2 / norm, cross(d, e1))]);
+    let qa = form(d, d);
+    let qb = 2.0 * form(p, d) + dot(base.e, d);
+    let qc = form(p, p) + dot(base.e, p) + base.f;
+    let roots = if qa.abs() <= TOLERANCE * norm {
+        if qb.abs() <= TOLERANCE {
+            Vec::new()
+        } else {
+            vec![-qc / qb]
+        }
+    } else {
+        let disc = qb * qb - 4.0 * qa * qc;
+        // Tangent configurations have a double root, up to rounding errors.
+        if disc < -TOLERANCE * qb * qb {
+            Vec::new()
+        } else {
+            let sqrt = disc.max(0.0).sqrt();
+            vec![(-qb - sqrt) / (2.0 * qa), (-qb + sqrt) / (2.0 * qa)]
+        }
+    };
+    roots
+        .into_iter()
+        .map(|t| sum(&[(1.0, p), (t, d)]))
+        .collect()
+}
+
+/// Every circle tangent to three points, lines or circles, sorted by radius.
+fn solutions(objs: &[GObject]) -> Option<Vec<Circle>> {
+    let cycles = objs
+        .iter()
+        .map(Cycle::orientations)
+        .collect::<Option<Vec<_>>>()?;
+    let mut circles: Vec<Circle> = Vec::new();
+    for choice in cycles.into_iter().multi_cartesian_product() {
+        for [x, y, r] in solve([choice[0], choice[1], choice[2]]) {
+            let same = |c: &Circle| {
+                (c.O.x - x).abs() <= TOLERANCE
+                    && (c.O.y - y).abs() <= TOLERANCE
+                    && (c.r - r).abs() <= TOLERANCE
+            };
+            if r > TOLERANCE && !circles.iter().any(same) {
+                circles.extend(Circle::from_center_radius(Point { x, y }, r));
+            }
+        }
+    }
+    circles.sort_by(|c, d| c.r.total_cmp(&d.r));
+    Some(circles)
+}
+
+/// `apollonius a, b, c` returns every circle tangent to `a`, `b` and `c`, and
+/// `apollonius a, b, c, n` only the `n`-th of them, counting from 1.
+pub fn apollonius(input: Vec<GObject>) -> Result<Vec<GObject>, FuncError> {
+    let arg_error = || {
+        FuncError::ArgError(
+            String::from("apollonius"),
+            input.iter().map(GObject::kind).collect(),
+            &[&[OBJECT; 3], &[OBJECT, OBJECT, OBJECT, "Number"]],
+        )
+    };
+    let (objs, selector) = match input.as_slice() {
+        [a, b, c, GObject::Number(n)] => ([*a, *b, *c], Some(*n)),
+        [a, b, c] => ([*a, *b, *c], None),
+        _ => return Err(arg_error()),
+    };
+    let circles = solutions(&objs).ok_or_else(arg_error)?;
+    if circles.is_empty() {
+        return Err(FuncError::NoApollonius);
+    }
+    match selector {
+        None => Ok(circles.into_iter().map(GObject::Circle).collect()),
+        Some(n) if n.fract() == 0.0 && n >= 1.0 && n as usize <= circles.len() => {
+            Ok(vec![GObject::Circle(circles[n as usize - 1])])
+        }
+        Some(n) => Err(FuncError::NoSolution(n, circles.len())),
+    }
+}
@@ -0,0 +1,131 @@
+// Triangle centers of the Encyclopedia of Triangle Centers, given by their
+// barycentric coordinates.
+#![allow(non_snake_case)]
+
+use crate::interpreter::utils::FuncError;
+use metric_rs::{
+    calc::{
+        basic::{angle, Distance},
+        trig::centers::from_barycentric,
+    },
+    objects::Point,
+};
+use std::f64::consts::PI;
+
+/// Side lengths and angles of a triangle, seen from one of its vertices.
+struct View {
+    a: f64,
+    b: f64,
+    c: f64,
+    A: f64,
+    B: f64,
+    C: f64,
+}
+
+impl View {
+    /// The same triangle, seen from the next vertex.
+    #[inline]
+    fn next(&self) -> Self {
+        View {
+            a: self.b,
+            b: self.c,
+            c: self.a,
+            A: self.B,
+            B: self.C,
+            C: self.A,
+        }
+    }
+}
+
+/// The first barycentric coordinate of `X(n)`; the others follow by cyclic
+/// permutation.
+fn barycentric(n: u32) -> Option<fn(&View) -> f64> {
+    let f: fn(&View) -> f64 = match n {
+        // Incenter
+        1 => |t| t.a,
+        // Centroid
+        2 => |_| 1.0,
+        // Circumcenter
+        3 => |t| t.a * t.a * (t.b * t.b + t.c * t.c - t.a * t.a),
+        // Orthocenter
+        4 => |t| t.A.tan(),
+        // Nine-point center
+        5 => |t| t.a * (t.B - t.C).cos(),
+        // Symmedian point
+        6 => |t| t.a * t.a,
+        // Gergonne point
+        7 => |t| 1.0 / (t.b + t.c - t.a),
+        // Nagel point
+        8 => |t| t.b + t.c - t.a,
+        // Mittenpunkt
+        9 => |t| t.a * (t.b + t.c - t.a),
+        // Spieker center
+        10 => |t| t.b + t.c,
+        // Feuerbach point
+        11 => |t| (t.b + t.c - t.a) * (t.b - t.c).powi(2),
+        // Fermat points
+        13 => |t| t.a / (t.A + PI / 3.0).sin(),
+        14 => |t| t.a / (t.A - PI / 3.0).sin(),
+        // Isodynamic points
+        15 => |t| t.a * (t.A + PI / 3.0).sin(),
+        16 => |t| t.a * (t.A - PI / 3.0).sin(),
+        // Napoleon points
+        17 => |t| t.a / (t.A + PI / 6.0).sin(),
+        18 => |t| t.a / (t.A - PI / 6.0).sin(),
+        // Clawson point
+        19 => |t| t.a * t.A.tan(),
+        // de Longchamps point
+        20 => |t| t.a * (t.A.cos() - t.B.cos() * t.C.cos()),
+        // Schiffler point
+        21 => |t| t.a / (t.B.cos() + t.C.cos()),
+        // Exeter point
+        22 => |t| t.a * t.a * (t.b.powi(4) + t.c.powi(4) - t.a.powi(4)),
+        // Far-out point
+        23 => |t| t.a * t.a * (t.b.powi(4) + t.c.powi(4) - t.a.powi(4) - t.b * t.b * t.c * t.c),
+        31 => |t| t.a.powi(3),
+        32 => |t| t.a.powi(4),
+        37 => |t| t.a * (t.b + t.c),
+        // Brocard midpoint
+        39 => |t| t.a * t.a * (t.b * t.b + t.c * t.c),
+        // Bevan point
+        40 => |t| t.a * (t.B.cos() + t.C.cos() - t.A.cos() - 1.0),
+        42 => |t| t.a * t.a * (t.b + t.c),
+        // Kosnita point
+        54 => |t| t.a / (t.B - t.C).cos(),
+        55 => |t| t.a * t.a * (t.b + t.c - t.a),
+        56 => |t| t.a * t.a / (t.b + t.c - t.a),
+        57 => |t| t.a / (t.b + t.c - t.a),
+        63 => |t| t.a * t.A.cos(),
+        69 => |t| t.b * t.b + t.c * t.c - t.a * t.a,
+        75 => |t| 1.0 / t.a,
+        76 => |t| 1.0 / (t.a * t.a),
+        100 => |t| t.a / (t.b - t.c),
+        110 => |t| t.a * t.a / (t.b * t.b - t.c * t.c),
+        _ => return None,
+    };
+    Some(f)
+}
+
+/// The center `X(n)` of a triangle.
+pub fn center(t: (Point, Point, Point), n: f64) -> Result<Point, FuncError> {
+    let f = (n.fract() == 0.0 && n >= 1.0)
+        .then(|| barycentric(n as u32))
+        .flatten()
+        .ok_or(FuncError::NoCenter(n))?;
+    let (A, B, C) = t;
+    let angle = |X, O, Y| angle(X, O, Y).map_err(FuncError::CalcError);
+    let view = View {
+        a: B.distance(C),
+        b: C.distance(A),
+        c: A.distance(B),
+        A: angle(B, A, C)?,
+        B: angle(C, B, A)?,
+        C: angle(A, C, B)?,
+    };
+    let coords = (f(&view), f(&view.next()), f(&view.next().next()));
+    // Like `X(100)` of an isosceles triangle.
+    if !(coords.0.is_finite() && coords.1.is_finite() && coords.2.is_finite()) {
+        return Err(FuncError::UndefinedCenter(n));
+    }
+    from_barycentric(t, coords).map_err(FuncError::CalcError)
+}
@@ -0,0 +1,99 @@
+// Circles related to a triangle. Those depending on a vertex are given for
+// each vertex, in order.
+#![allow(non_snake_case)]
+
+use super::centers::center;
+use crate::interpreter::utils::FuncError;
+use metric_rs::{
+    calc::{
+        basic::{angle, Distance},
+        construct::projection,
+        trig::centers::excenter,
+    },
+    objects::{Circle, Line, Point},
+};
+
+type Trig = (Point, Point, Point);
+
+#[inline]
+fn circle(O: Point, r: f64) -> Result<Circle, FuncError> {
+    Circle::from_center_radius(O, r).map_err(FuncError::CalcError)
+}
+
+#[inline]
+fn side(A: Point, B: Point) -> Result<Line, FuncError> {
+    Line::from_2p(A, B).map_err(FuncError::CalcError)
+}
+
+/// The triangle seen from each of its vertices.
+#[inline]
+fn rotations((A, B, C): Trig) -> [Trig; 3] {
+    [(A, B, C), (B, C, A), (C, A, B)]
+}
+
+pub fn circumcircle((A, B, C): Trig) -> Result<Circle, FuncError> {
+    Circle::from_3p(A, B, C).map_err(FuncError::CalcError)
+}
+
+pub fn incircle(t: Trig) -> Result<Circle, FuncError> {
+    let I = center(t, 1.0)?;
+    circle(I, I.distance(side(t.1, t.2)?))
+}
+
+/// The excircle in angle `A`.
+fn excircle(t: Trig) -> Result<Circle, FuncError> {
+    let J = excenter(t).map_err(FuncError::CalcError)?;
+    circle(J, J.distance(side(t.1, t.2)?))
+}
+
+/// The excircles in angles `A`, `B` and `C`.
+pub fn excircles(t: Trig) -> Result<[Circle; 3], FuncError> {
+    let [a, b, c] = rotations(t);
+    Ok([excircle(a)?, excircle(b)?, excircle(c)?])
+}
+
+pub fn nine_point(t: Trig) -> Result<Circle, FuncError> {
+    let N = center(t, 5.0)?;
+    circle(N, N.distance((t.1 + t.2) / 2.0))
+}
+
+/// The circle centered at the orthocenter, with respect to which the
+/// triangle is self-polar. It only exists for obtuse triangles.
+pub fn polar_circle(t: Trig) -> Result<Circle, FuncError> {
+    let (A, B, C) = t;
+    let H = center(t, 4.0)?;
+    let D = projection(A, side(B, C)?);
+    // The power of `H` with respect to the circle with diameter `AD`.
+    let (u, v) = (A - H, D - H);
+    let power = u.x * v.x + u.y * v.y;
+    if power <= 0.0 {
+        return Err(FuncError::NoPolarCircle);
+    }
+    circle(H, power.sqrt())
+}
+
+/// The incircle of the medial triangle.
+pub fn spieker(t: Trig) -> Result<Circle, FuncError> {
+    let S = center(t, 10.0)?;
+    let M = (t.1 + t.2) / 2.0;
+    let N = (t.2 + t.0) / 2.0;
+    circle(S, S.distance(side(M, N)?))
+}
+
+/// The mixtilinear incircle in angle `A`, tangent to `AB`, `AC` and
+/// internally to the circumcircle.
+fn mixtilinear(t: Trig) -> Result<Circle, FuncError> {
+    let (A, B, C) = t;
+    let I = center(t, 1.0)?;
+    let half = angle(B, A, C).map_err(FuncError::CalcError)? / 2.0;
+    // The center is on `AI`, and the radius is `r / cos^2(A / 2)`.
+    let k = 1.0 / half.cos().powi(2);
+    let K = A + (I - A) * k;
+    circle(K, I.distance(side(A, B)?) * k)
+}
+
+/// The mixtilinear incircles in angles `A`, `B` and `C`.
+pub fn mixtilinears(t: Trig) -> Result<[Circle; 3], FuncError> {
+    let [a, b, c] = rotations(t);
+    Ok([mixtilinear(a)?, mixtilinear(b)?, mixtilinear(c)?])
+}
@@ -1,7 +1,27 @@
-use crate::interpreter::{structs::Segment, utils::FuncError, utils::GObject};
+use crate::interpreter::{
+    builtin::{
+        apollonius::apollonius,
+        centers::center,
+        circles::*,
+        predicates::TOLERANCE,
+        projective::{cross_ratio, harmonic_conjugate},
+        transforms::apply,
+        triangles,
+    },
+    structs::{
+        conic::{Conic, ConicError},
+        transform::Transform,
+        Segment,
+    },
+    utils::FuncError,
+    utils::{GObject, Keywords},
+};
 use lazy_static::lazy_static;
 use metric_rs::{
-    calc::{basic::*, construct::*, point_on::PointOn, transform::*, trig::centers::*},
+    calc::{
+        basic::*, construct::*, exception::CalcException, point_on::PointOn, transform::*,
+        trig::centers::*,
+    },
     objects::*,
 };
 use std::collections::HashMap;
@@ -16,46 +36,228 @@ impl From<LineInverted> for GObject {
     }
 }
 
+impl From<Point> for GObject {
+    #[inline]
+    fn from(value: Point) -> Self {
+        Self::Point(value)
+    }
+}
+
+impl From<Line> for GObject {
+    #[inline]
+    fn from(value: Line) -> Self {
+        Self::Line(value)
+    }
+}
+
+impl From<Circle> for GObject {
+    #[inline]
+    fn from(value: Circle) -> Self {
+        Self::Circle(value)
+    }
+}
+
+impl From<Conic> for GObject {
+    #[inline]
+    fn from(value: Conic) -> Self {
+        Self::Conic(value)
+    }
+}
+
+impl From<(Point, Point, Point)> for GObject {
+    #[inline]
+    fn from(value: (Point, Point, Point)) -> Self {
+        Self::Trig(value)
+    }
+}
+
+impl From<f64> for GObject {
+    #[inline]
+    fn from(value: f64) -> Self {
+        Self::Number(value)
+    }
+}
+
+impl From<Transform> for GObject {
+    #[inline]
+    fn from(value: Transform) -> Self {
+        Self::Transform(value)
+    }
+}
+
+impl From<Option<Point>> for GObject {
+    #[inline]
+    fn from(value: Option<Point>) -> Self {
+        value.map_or(Self::None, Self::Point)
+    }
+}
+
+/// Results of methods on conics, whose errors are not `CalcException`s.
+#[inline]
+fn conic<T: Into<GObject>, U: Into<GObject>>(
+    result: Result<(T, U), ConicError>,
+) -> Result<Vec<GObject>, FuncError> {
+    let (x, y) = result?;
+    Ok(vec![x.into(), y.into()])
+}
+
+/// The result of a method returning a single value, whose errors are not
+/// `CalcException`s.
+#[inline]
+fn single<T: Into<GObject>, E: Into<FuncError>>(
+    result: Result<T, E>,
+) -> Result<Vec<GObject>, FuncError> {
+    match result {
+        Ok(x) => Ok(vec![x.into()]),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The result of a method returning one value for each vertex of a triangle.
+#[inline]
+fn per_vertex<T: Into<GObject>>(
+    result: Result<[T; 3], FuncError>,
+) -> Result<Vec<GObject>, FuncError> {
+    Ok(result?.into_iter().map(Into::into).collect())
+}
+
+/// The triangle formed by the images of the vertices.
+#[inline]
+fn vertices<E>(
+    (a, b, c): (Point, Point, Point),
+    f: impl Fn(Point) -> Result<Point, E>,
+) -> Result<(Point, Point, Point), E> {
+    Ok((f(a)?, f(b)?, f(c)?))
+}
+
+/// Fail if a method taking no keyword argument is given one.
+#[inline]
+fn no_keywords(name: &str, keywords: Keywords) -> Result<(), FuncError> {
+    match keywords.into_keys().next() {
+        Some(key) => Err(FuncError::NoKeyword(String::from(name), key)),
+        None => Ok(()),
+    }
+}
+
+/// The line containing a ray.
+#[inline]
+fn ray_line((a, b): (Point, Point)) -> Result<Line, FuncError> {
+    Line::from_2p(a, b).map_err(FuncError::CalcError)
+}
+
+/// Keep the intersections lying on all the rays, in order, and fail if there
+/// is none.
+fn on_rays(
+    rays: &[(Point, Point)],
+    points: Result<Vec<Point>, CalcException>,
+) -> Result<Vec<GObject>, FuncError> {
+    let points: Vec<GObject> = points
+        .map_err(FuncError::CalcError)?
+        .into_iter()
+        .filter(|&p| {
+            rays.iter().all(|&(a, b)| {
+                let (u, v) = (b - a, p - a);
+                u.x * v.x + u.y * v.y >= -TOLERANCE * a.distance(b)
+            })
+        })
+        .map(GObject::Point)
+        .collect();
+    if points.is_empty() {
+        Err(FuncError::OffRay)
+    } else {
+        Ok(points)
+    }
+}
+
+/// Both intersections of two objects.
+#[inline]
+fn pair(result: Result<(Point, Point), CalcException>) -> Result<Vec<GObject>, FuncError> {
+    let (p, q) = result.map_err(FuncError::CalcError)?;
+    Ok(vec![GObject::Point(p), GObject::Point(q)])
+}
+
+/// Leave out the intersections at `except`, if it is given, and fail if none
+/// is left.
+fn other_than(
+    values: Result<Vec<GObject>, FuncError>,
+    except: Option<Point>,
+) -> Result<Vec<GObject>, FuncError> {
+    let Some(p) = except else {
+        return values;
+    };
+    let values: Vec<GObject> = values?
+        .into_iter()
+        .filter(|x| !matches!(x, GObject::Point(q) if q.distance(p) < TOLERANCE))
+        .collect();
+    if values.is_empty() {
+        Err(FuncError::NoOther)
+    } else {
+        Ok(values)
+    }
+}
+
 macro_rules! ret_branch {
+    ([$(<$var:ident>$param:ident),+] => <Vec, GObject>$body:expr) => {
+        return $body
+    };
     ([$(<$var:ident>$param:ident),+] => <dyn, None>$body:expr) => {
         return match $body {
             Err(e) => Err(FuncError::CalcError(e)),
-            Ok(x) => Ok((GObject::from(x), GObject::None)),
+            Ok(x) => Ok(vec![GObject::from(x)]),
         }
     };
     ([$(<$var:ident>$param:ident),+] => <$ret:ident, None>$body:expr) => {
         return match $body {
             Err(e) => Err(FuncError::CalcError(e)),
-            Ok(x) => Ok((GObject::$ret(x), GObject::None)),
+            Ok(x) => Ok(vec![GObject::$ret(x)]),
         }
     };
     ([$(<$var:ident>$param:ident),+] => <$ret1:ident,$ret2:ident>$body:expr) => {
         return match $body {
             Err(e) => Err(FuncError::CalcError(e)),
-            Ok((x, y)) => Ok((GObject::$ret1(x), GObject::$ret2(y))),
+            Ok((x, y)) => Ok(vec![GObject::$ret1(x), GObject::$ret2(y)]),
         }
     };
 }
 
 macro_rules! entry {
-    ($name:literal; $([$(<$var:ident>$param:ident),+] => <$ret1:ident,$ret2:ident>$body:expr),+) => {
+    ($name:literal; $([$(<$var:ident>$param:ident),+] $([$($key:ident: $kind:ident),+])? => <$ret1:ident,$ret2:ident>$body:expr),+) => {
         (
             String::from($name),
-            (|input: Vec<GObject>| {
+            (|input: Vec<GObject>, #[allow(unused_mut)] mut keywords: Keywords| {
                 let slice = input.as_slice();
                 $(
                     if let [$(GObject::$var($param)),+] = slice {
                         $(let $param = *$param;)+
+                        // Optional keyword arguments of this overload, bound to
+                        // variables of the same name.
+                        $($(
+                            let $key = match keywords.remove(stringify!($key)) {
+                                None => None,
+                                Some(GObject::$kind(x)) => Some(x),
+                                Some(obj) => return Err(FuncError::KeywordError(
+                                    String::from($name),
+                                    String::from(stringify!($key)),
+                                    stringify!($kind),
+                                    obj.kind(),
+                                )),
+                            };
+                        )+)?
+                        no_keywords($name, keywords)?;
                         ret_branch!([$(<$var>$param),+] => <$ret1, $ret2>$body);
                     }
                 )+
-                Err(FuncError::ArgError)
+                Err(FuncError::ArgError(
+                    String::from($name),
+                    slice.iter().map(GObject::kind).collect(),
+                    &[$(&[$(stringify!($var)),+]),+],
+                ))
             }) as _,
         )
     };
 }
 
-type GFunction = fn(Vec<GObject>) -> Result<(GObject, GObject), FuncError>;
+type GFunction = fn(Vec<GObject>, Keywords) -> Result<Vec<GObject>, FuncError>;
 
 lazy_static! {
     pub static ref FUNCTIONS: HashMap<String, GFunction> =
@@ -68,9 +270,18 @@ lazy_static! {
                 [<Circle>c, <Line>l, <Point>p] => <Point, Point>l.inter_common(c, p),
                 [<Circle>c, <Circle>d, <Point>p] => <Point, Point>c.inter_common(d, p),
                 [<Line>l, <Line>k] => <Point, None>l.inter(k),
-                [<Line>l, <Circle>c] => <Point, Point>l.inter(c),
-                [<Circle>c, <Line>l] => <Point, Point>l.inter(c),
-                [<Circle>c, <Circle>d] => <Point, Point>c.inter(d)
+                [<Line>l, <Circle>c] [except: Point] => <Vec, GObject>other_than(pair(l.inter(c)), except),
+                [<Circle>c, <Line>l] [except: Point] => <Vec, GObject>other_than(pair(l.inter(c)), except),
+                [<Circle>c, <Circle>d] [except: Point] => <Vec, GObject>other_than(pair(c.inter(d)), except),
+                [<Ray>r, <Line>l] => <Vec, GObject>on_rays(&[r], ray_line(r)?.inter(l).map(|p| vec![p])),
+                [<Line>l, <Ray>r] => <Vec, GObject>on_rays(&[r], ray_line(r)?.inter(l).map(|p| vec![p])),
+                [<Ray>r, <Ray>s] => <Vec, GObject>on_rays(&[r, s], ray_line(r)?.inter(ray_line(s)?).map(|p| vec![p])),
+                [<Ray>r, <Circle>c] [except: Point] => <Vec, GObject>other_than(on_rays(&[r], ray_line(r)?.inter(c).map(|(p, q)| vec![p, q])), except),
+                [<Circle>c, <Ray>r] [except: Point] => <Vec, GObject>other_than(on_rays(&[r], ray_line(r)?.inter(c).map(|(p, q)| vec![p, q])), except),
+                [<Line>l, <Conic>c, <Point>p] => <Vec, GObject>conic(c.inter_common(l, p)),
+                [<Conic>c, <Line>l, <Point>p] => <Vec, GObject>conic(c.inter_common(l, p)),
+                [<Line>l, <Conic>c] [except: Point] => <Vec, GObject>other_than(conic(c.inter(l)), except),
+                [<Conic>c, <Line>l] [except: Point] => <Vec, GObject>other_than(conic(c.inter(l)), except)
             ),
             entry!(
                 "perp";
@@ -95,7 +306,8 @@ lazy_static! {
             ),
             entry!(
                 "tan";
-                [<Point>a, <Circle>c] => <Line, Line>tangent(a, c)
+                [<Point>a, <Circle>c] => <Line, Line>tangent(a, c),
+                [<Point>a, <Conic>c] => <Vec, GObject>conic(c.tangents(a))
             ),
             entry!(
                 "outer-tan";
@@ -115,15 +327,21 @@ lazy_static! {
             ),
             entry!(
                 "polar";
-                [<Point>a, <Circle>c] => <Line, None>polar_line(a, c)
+                [<Point>a, <Circle>c] => <Line, None>polar_line(a, c),
+                [<Point>a, <Conic>c] => <Vec, GObject>single(c.polar(a))
             ),
+            (String::from("apollonius"), (|input, keywords| {
+                no_keywords("apollonius", keywords)?;
+                apollonius(input)
+            }) as GFunction),
             // Point on object
             entry!(
                 "on";
-                [<Circle>c, <Number>x] => <Point, None>Ok(c.point_on(x)),
-                [<Point>from, <Point>to, <Number>x] => <Point, None>Ok(
-                    Segment { from, to }.point_on(x)
-                )
+                [<Circle>c, <Number>x] [from: Point] => <Point, None>Ok(
+                    // Angles are counted from the direction of `from`.
+                    c.point_on(x + from.map_or(0.0, |p| (p.y - c.O.y).atan2(p.x - c.O.x)))
+                ),
+                [<Point>a, <Point>b, <Number>x] => <Point, None>Ok(Segment { from: a, to: b }.point_on(x))
             ),
             // Transformation
             entry!(
@@ -132,26 +350,77 @@ lazy_static! {
                 [<Line>a, <Point>b] => <Line, None>Ok(a.reflect_in(b)),
                 [<Circle>a, <Point>b] => <Circle, None>Ok(a.reflect_in(b)),
                 [<Point>a, <Line>b] => <Point, None>Ok(a.reflect_in(b)),
-                [<Line>a, <Line>b] => <Line, None>Ok(a.reflect_in(b))
+                [<Line>a, <Line>b] => <Line, None>Ok(a.reflect_in(b)),
+                [<Conic>a, <Point>b] => <Conic, None>Ok(a.reflect_in(b)),
+                [<Conic>a, <Line>b] => <Conic, None>Ok(a.reflect_in(b)),
+                [<Circle>a, <Line>b] => <Circle, None>Ok(Circle { O: a.O.reflect_in(b), r: a.r }),
+                [<Trig>t, <Point>b] => <Trig, None>vertices(t, |p| Ok(p.reflect_in(b))),
+                [<Trig>t, <Line>b] => <Trig, None>vertices(t, |p| Ok(p.reflect_in(b))),
+                [<Point>a, <Circle>c] => <Point, None>a.invert_in(c.O, c.r),
+                [<Line>l, <Circle>c] => <dyn, None>Ok(l.invert_in(c.O, c.r)),
+                [<Circle>d, <Circle>c] => <dyn, None>Ok(d.invert_in(c.O, c.r)),
+                [<Trig>t, <Circle>c] => <Trig, None>vertices(t, |p| p.invert_in(c.O, c.r)),
+                [<Point>o] => <Transform, None>Ok(Transform::scaling(o, -1.0)),
+                [<Line>l] => <Vec, GObject>single(Transform::reflection(l))
             ),
             entry!(
                 "scl";
                 [<Point>a, <Point>b, <Number>c] => <Point, None>Ok(a.scale(b, c)),
                 [<Line>a, <Point>b, <Number>c] => <Line, None>Ok(a.scale(b, c)),
-                [<Circle>a, <Point>b, <Number>c] => <Circle, None>Ok(a.scale(b, c))
+                [<Circle>a, <Point>b, <Number>c] => <Circle, None>Ok(a.scale(b, c)),
+                [<Conic>a, <Point>b, <Number>c] => <Conic, None>Ok(a.scale(b, c)),
+                [<Trig>t, <Point>b, <Number>c] => <Trig, None>vertices(t, |p| Ok(p.scale(b, c))),
+                [<Point>o, <Number>k] => <Transform, None>Ok(Transform::scaling(o, k))
             ),
             entry!(
                 "rot";
                 [<Point>a, <Point>b, <Number>t] => <Point, None>Ok(a.rotate(b, t)),
                 [<Line>a, <Point>b, <Number>t] => <Line, None>Ok(a.rotate(b, t)),
-                [<Circle>a, <Point>b, <Number>t] => <Circle, None>Ok(a.rotate(b, t))
+                [<Circle>a, <Point>b, <Number>t] => <Circle, None>Ok(a.rotate(b, t)),
+                [<Conic>a, <Point>b, <Number>t] => <Conic, None>Ok(a.rotate(b, t)),
+                [<Trig>a, <Point>b, <Number>t] => <Trig, None>vertices(a, |p| Ok(p.rotate(b, t))),
+                [<Point>o, <Number>t] => <Transform, None>Ok(Transform::rotation(o, t))
             ),
             entry!(
                 "inv";
                 [<Point>a, <Circle>c] => <Point, None>a.invert_in(c.O, c.r),
                 [<Line>l, <Circle>c] => <dyn, None>Ok(l.invert_in(c.O, c.r)),
-                [<Circle>d, <Circle>c] => <dyn, None>Ok(d.invert_in(c.O, c.r))
+                [<Circle>d, <Circle>c] => <dyn, None>Ok(d.invert_in(c.O, c.r)),
+                [<Trig>t, <Circle>c] => <Trig, None>vertices(t, |p| p.invert_in(c.O, c.r))
+            ),
+            entry!(
+                "spiral";
+                [<Point>o, <Number>k, <Number>t] => <Transform, None>Ok(Transform::spiral(o, k, t)),
+                [<Point>a, <Point>b, <Point>c, <Point>d] => <Vec, GObject>single(Transform::spiral_2p(a, b, c, d))
+            ),
+            entry!(
+                "translate";
+                [<Point>v] => <Transform, None>Ok(Transform::translation(v)),
+                [<Point>a, <Point>b] => <Transform, None>Ok(Transform::translation(b - a))
+            ),
+            entry!(
+                "affine";
+                [<Point>a, <Point>b, <Point>c, <Point>p, <Point>q, <Point>r] => <Vec, GObject>single(
+                    Transform::affine([a, b, c], [p, q, r])
+                )
+            ),
+            entry!(
+                "homography";
+                [<Point>a, <Point>b, <Point>c, <Point>d, <Point>p, <Point>q, <Point>r, <Point>s] => <Vec, GObject>single(
+                    Transform::homography([a, b, c, d], [p, q, r, s])
+                )
             ),
+            entry!("compose"; [<Transform>f, <Transform>g] => <Transform, None>Ok(g.then(f))),
+            (String::from("apply"), (|input, keywords| {
+                no_keywords("apply", keywords)?;
+                apply(input)
+            }) as GFunction),
+            // Projective geometry
+            entry!(
+                "cross-ratio";
+                [<Point>a, <Point>b, <Point>c, <Point>d] => <Vec, GObject>single(cross_ratio(a, b, c, d))
+            ),
+            entry!("harm-conj"; [<Point>a, <Point>b, <Point>c] => <Vec, GObject>single(harmonic_conjugate(a, b, c))),
             // Object creation
             entry!(
                 "l";
@@ -163,6 +432,14 @@ lazy_static! {
                 "circ";
                 [<Circle>c] => <Point, Number>Ok((c.O, c.r))
             ),
+            entry!(
+                "conic";
+                [<Point>a, <Point>b, <Point>c, <Point>d, <Point>e] => <Vec, GObject>single(Conic::from_5p([a, b, c, d, e])),
+                [<Point>f, <Line>l, <Number>e] => <Vec, GObject>single(Conic::from_focus_directrix(f, l, e))
+            ),
+            entry!("parabola"; [<Point>f, <Line>l] => <Vec, GObject>single(Conic::from_focus_directrix(f, l, 1.0))),
+            entry!("ellipse"; [<Point>f, <Point>g, <Point>p] => <Vec, GObject>single(Conic::ellipse(f, g, p))),
+            entry!("hyperbola"; [<Point>f, <Point>g, <Point>p] => <Vec, GObject>single(Conic::hyperbola(f, g, p))),
             // Triangle centers
             entry!("cO"; [<Trig>t] => <Point, None>circum(t)),
             entry!("cI"; [<Trig>t] => <Point, None>incenter(t)),
@@ -171,6 +448,22 @@ lazy_static! {
             entry!("cH"; [<Trig>t] => <Point, None>ortho(t)),
             entry!("cK"; [<Trig>t] => <Point, None>symmedian(t)),
             entry!("cGe"; [<Trig>t] => <Point, None>gergonne(t)),
+            entry!("X"; [<Trig>t, <Number>n] => <Vec, GObject>single(center(t, n))),
+            // Triangle circles
+            entry!("circumcircle"; [<Trig>t] => <Vec, GObject>single(circumcircle(t))),
+            entry!("incircle"; [<Trig>t] => <Vec, GObject>single(incircle(t))),
+            entry!("excircle"; [<Trig>t] => <Vec, GObject>per_vertex(excircles(t))),
+            entry!("nine-point"; [<Trig>t] => <Vec, GObject>single(nine_point(t))),
+            entry!("polar-circle"; [<Trig>t] => <Vec, GObject>single(polar_circle(t))),
+            entry!("spieker"; [<Trig>t] => <Vec, GObject>single(spieker(t))),
+            entry!("mixtilinear"; [<Trig>t] => <Vec, GObject>per_vertex(mixtilinears(t))),
+            // Derived triangles
+            entry!("medial"; [<Trig>t] => <dyn, None>Ok(triangles::medial(t))),
+            entry!("orthic"; [<Trig>t] => <Vec, GObject>single(triangles::orthic(t))),
+            entry!("excentral"; [<Trig>t] => <Vec, GObject>single(triangles::excentral(t))),
+            entry!("pedal"; [<Trig>t, <Point>p] => <Vec, GObject>single(triangles::pedal(t, p))),
+            entry!("cevian"; [<Trig>t, <Point>p] => <Vec, GObject>single(triangles::cevian(t, p))),
+            entry!("anticevian"; [<Trig>t, <Point>p] => <Vec, GObject>single(triangles::anticevian(t, p))),
             entry!(
                 "bary";
                 [<Trig>t, <Number>x, <Number>y, <Number>z] => <Point, None>from_barycentric(t, (x, y, z))
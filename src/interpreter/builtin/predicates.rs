@@ -0,0 +1,183 @@
+use crate::interpreter::utils::{FuncError, GObject};
+use itertools::Itertools;
+use lazy_static::lazy_static;
+use metric_rs::{
+    calc::basic::Distance,
+    objects::{Line, Point},
+};
+use std::collections::HashMap;
+
+/// Default tolerance of numeric comparisons.
+pub const TOLERANCE: f64 = 1e-6;
+
+macro_rules! entry {
+    ($name:literal; $([$(<$var:ident>$param:ident),+] => $body:expr),+) => {
+        (
+            String::from($name),
+            (|input: Vec<GObject>| {
+                let slice = input.as_slice();
+                $(
+                    if let [$(GObject::$var($param)),+] = slice {
+                        $(let $param = *$param;)+
+                        return Ok($body);
+                    }
+                )+
+                Err(FuncError::ArgError(
+                    String::from($name),
+                    slice.iter().map(GObject::kind).collect(),
+                    &[$(&[$(stringify!($var)),+]),+],
+                ))
+            }) as _,
+        )
+    };
+}
+
+type GPredicate = fn(Vec<GObject>) -> Result<Residual, FuncError>;
+
+/// How far a predicate is from holding, and the tolerance under which it holds.
+#[derive(Debug, Clone, Copy)]
+pub struct Residual {
+    pub value: f64,
+    pub tolerance: f64,
+}
+
+impl Residual {
+    #[inline]
+    pub fn holds(self) -> bool {
+        self.value <= self.tolerance
+    }
+}
+
+#[inline]
+fn near(x: f64) -> Residual {
+    Residual {
+        value: x.abs(),
+        tolerance: TOLERANCE,
+    }
+}
+
+/// Coefficients of a line, scaled so that `a^2 + b^2 = 1`.
+#[inline]
+fn normalize(l: Line) -> (f64, f64, f64) {
+    let norm = l.a.hypot(l.b);
+    (l.a / norm, l.b / norm, l.c / norm)
+}
+
+#[inline]
+fn cross(o: Point, a: Point, b: Point) -> f64 {
+    (a.x - o.x) * (b.y - o.y) - (a.y - o.y) * (b.x - o.x)
+}
+
+#[inline]
+fn det3(a: (f64, f64, f64), b: (f64, f64, f64), c: (f64, f64, f64)) -> f64 {
+    a.0 * (b.1 * c.2 - b.2 * c.1) - a.1 * (b.0 * c.2 - b.2 * c.0) + a.2 * (b.0 * c.1 - b.1 * c.0)
+}
+
+/// Divide `x` by `scale`, or give 0 if the points measured by `scale` coincide.
+#[inline]
+fn relative(x: f64, scale: f64) -> f64 {
+    if scale == 0.0 {
+        0.0
+    } else {
+        x / scale
+    }
+}
+
+/// Whether three points lie on a line.
+#[inline]
+fn collinear(a: Point, b: Point, c: Point) -> Residual {
+    // The sine of the angle between the two longest sides, which does not
+    // depend on the size of the triangle.
+    let mut sides = [a.distance(b), b.distance(c), c.distance(a)];
+    sides.sort_by(f64::total_cmp);
+    near(relative(cross(a, b, c), sides[1] * sides[2]))
+}
+
+/// Whether three lines pass through a point. Parallel lines never do.
+#[inline]
+fn concurrent(l: Line, k: Line, m: Line) -> Residual {
+    // The sine of the angle between two normalized lines.
+    let sin = |l: (f64, f64, f64), k: (f64, f64, f64)| l.0 * k.1 - l.1 * k.0;
+    // The two lines crossing at the largest angle give the most precise
+    // intersection, whose distance to the third line is compared.
+    let (l, k, m) = [(l, k, m), (k, m, l), (m, l, k)]
+        .map(|(l, k, m)| (normalize(l), normalize(k), normalize(m)))
+        .into_iter()
+        .max_by(|(l, k, _), (l1, k1, _)| sin(*l, *k).abs().total_cmp(&sin(*l1, *k1).abs()))
+        .unwrap();
+    let w = sin(l, k);
+    if w.abs() < TOLERANCE {
+        return Residual {
+            value: f64::INFINITY,
+            tolerance: TOLERANCE,
+        };
+    }
+    let x = (l.1 * k.2 - l.2 * k.1) / w;
+    let y = (l.2 * k.0 - l.0 * k.2) / w;
+    near(m.0 * x + m.1 * y + m.2)
+}
+
+/// Whether four points lie on a circle or a line.
+#[inline]
+fn concyclic(a: Point, b: Point, c: Point, d: Point) -> Residual {
+    // The determinant of rows `(x^2 + y^2, x, y, 1)`, expanded along the
+    // last column after subtracting the row of `d` from the others. It is
+    // divided by the fourth power of the spread of the points, so it does not
+    // depend on the size of the figure.
+    let row = |p: Point| {
+        let (x, y) = (p.x - d.x, p.y - d.y);
+        (x * x + y * y, x, y)
+    };
+    let spread = [a, b, c, d]
+        .into_iter()
+        .tuple_combinations()
+        .map(|(p, q)| p.distance(q))
+        .fold(0.0, f64::max);
+    near(relative(det3(row(a), row(b), row(c)), spread.powi(4)))
+}
+
+lazy_static! {
+    pub static ref PREDICATES: HashMap<String, GPredicate> = HashMap::from([
+        entry!(
+            "collinear";
+            [<Point>a, <Point>b, <Point>c] => collinear(a, b, c)
+        ),
+        entry!(
+            "concyclic";
+            [<Point>a, <Point>b, <Point>c, <Point>d] => concyclic(a, b, c, d)
+        ),
+        entry!(
+            "concurrent";
+            [<Line>l, <Line>k, <Line>m] => concurrent(l, k, m)
+        ),
+        entry!(
+            "perp";
+            [<Line>l, <Line>k] => {
+                let (l, k) = (normalize(l), normalize(k));
+                near(l.0 * k.0 + l.1 * k.1)
+            }
+        ),
+        entry!(
+            "par";
+            [<Line>l, <Line>k] => {
+                let (l, k) = (normalize(l), normalize(k));
+                near(l.0 * k.1 - l.1 * k.0)
+            }
+        ),
+        entry!(
+            "on";
+            [<Point>p, <Line>l] => near(p.distance(l)),
+            [<Point>p, <Circle>c] => near(p.distance(c.O) - c.r),
+            [<Point>p, <Conic>c] => near(p.distance(c))
+        ),
+        entry!(
+            "eq";
+            [<Number>x, <Number>y] => near(x - y),
+            [<Number>x, <Number>y, <Number>tolerance] => Residual {
+                value: (x - y).abs(),
+                tolerance,
+            },
+            [<Point>p, <Point>q] => near(p.distance(q))
+        ),
+    ]);
+}
@@ -0,0 +1,48 @@
+// Cross-ratios and harmonic conjugates, computed with points as complex
+// numbers, so that they also work on circles.
+#![allow(non_snake_case)]
+
+use super::predicates::TOLERANCE;
+use crate::interpreter::utils::FuncError;
+use metric_rs::{calc::exception::CalcException, objects::Point};
+
+#[inline]
+fn mul(p: Point, q: Point) -> Point {
+    Point {
+        x: p.x * q.x - p.y * q.y,
+        y: p.x * q.y + p.y * q.x,
+    }
+}
+
+#[inline]
+fn div(p: Point, q: Point) -> Result<Point, FuncError> {
+    let n = q.x * q.x + q.y * q.y;
+    if n <= TOLERANCE * TOLERANCE {
+        return Err(FuncError::CalcError(CalcException::Degenerate));
+    }
+    Ok(Point {
+        x: (p.x * q.x + p.y * q.y) / n,
+        y: (p.y * q.x - p.x * q.y) / n,
+    })
+}
+
+/// The cross-ratio `(A, B; C, D) = (CA / CB) / (DA / DB)` of four collinear or
+/// concyclic points.
+pub fn cross_ratio(A: Point, B: Point, C: Point, D: Point) -> Result<f64, FuncError> {
+    let z = div(mul(C - A, D - B), mul(C - B, D - A))?;
+    // The cross-ratio is real exactly when the points are collinear or
+    // concyclic.
+    if z.y.abs() > TOLERANCE * z.x.abs().max(1.0) {
+        return Err(FuncError::NotConcyclic);
+    }
+    Ok(z.x)
+}
+
+/// The point `D` such that `(A, B; C, D) = -1`, on the line or circle through
+/// the three points.
+pub fn harmonic_conjugate(A: Point, B: Point, C: Point) -> Result<Point, FuncError> {
+    // `D = (C(A + B) - 2AB) / (2C - A - B)`
+    let n = mul(C, A + B) - mul(A, B) * 2.0;
+    let d = C * 2.0 - A - B;
+    div(n, d).map_err(|_| FuncError::AtInfinity)
+}
@@ -0,0 +1,45 @@
+// Applying transformations given as objects.
+
+use crate::interpreter::{
+    structs::transform::{CircleImage, Transform},
+    utils::{FuncError, GObject},
+};
+
+const OBJECT: &str = "Point or Line or Ray or Circle or Conic or Trig";
+
+/// The image of an object, or `None` if it cannot be transformed.
+fn image(f: &Transform, obj: GObject) -> Option<Result<GObject, FuncError>> {
+    let result = match obj {
+        GObject::Point(p) => f.point(p).map(GObject::Point),
+        GObject::Line(l) => f.line(l).map(GObject::Line),
+        GObject::Ray(r) => f.ray(r).map(GObject::Ray),
+        GObject::Circle(c) => f.circle(c).map(|c| match c {
+            CircleImage::Circle(c) => GObject::Circle(c),
+            CircleImage::Conic(c) => GObject::Conic(c),
+        }),
+        GObject::Conic(c) => f.conic(c).map(GObject::Conic),
+        GObject::Trig((a, b, c)) => f
+            .point(a)
+            .and_then(|a| Ok(GObject::Trig((a, f.point(b)?, f.point(c)?)))),
+        _ => return None,
+    };
+    Some(result.map_err(FuncError::from))
+}
+
+/// `apply f, X, Y, ...` returns the images of every object.
+pub fn apply(input: Vec<GObject>) -> Result<Vec<GObject>, FuncError> {
+    let arg_error = || {
+        FuncError::ArgError(
+            String::from("apply"),
+            input.iter().map(GObject::kind).collect(),
+            &[&["Transform", OBJECT, "..."]],
+        )
+    };
+    let (f, objs) = match input.as_slice() {
+        [GObject::Transform(f), objs @ ..] if !objs.is_empty() => (f, objs),
+        _ => return Err(arg_error()),
+    };
+    objs.iter()
+        .map(|&obj| image(f, obj).unwrap_or_else(|| Err(arg_error())))
+        .collect()
+}
@@ -0,0 +1,80 @@
+// Triangles derived from a triangle, and possibly a point. Vertices are listed
+// opposite to `A`, `B` and `C` in this order.
+#![allow(non_snake_case)]
+
+use crate::interpreter::utils::FuncError;
+use metric_rs::{
+    calc::{
+        construct::{midpoint, projection},
+        trig::centers::{excenter, from_barycentric},
+    },
+    objects::{Line, Point},
+};
+
+type Trig = (Point, Point, Point);
+
+#[inline]
+fn side(A: Point, B: Point) -> Result<Line, FuncError> {
+    Line::from_2p(A, B).map_err(FuncError::CalcError)
+}
+
+/// Barycentric coordinates of `P`, given by signed areas.
+#[inline]
+fn barycentric((A, B, C): Trig, P: Point) -> (f64, f64, f64) {
+    let area = |p: Point, q: Point, r: Point| (q.x - p.x) * (r.y - p.y) - (q.y - p.y) * (r.x - p.x);
+    (area(P, B, C), area(A, P, C), area(A, B, P))
+}
+
+#[inline]
+fn point(t: Trig, coords: (f64, f64, f64)) -> Result<Point, FuncError> {
+    from_barycentric(t, coords).map_err(FuncError::CalcError)
+}
+
+pub fn medial((A, B, C): Trig) -> Trig {
+    (midpoint(B, C), midpoint(C, A), midpoint(A, B))
+}
+
+/// The feet of the altitudes.
+pub fn orthic((A, B, C): Trig) -> Result<Trig, FuncError> {
+    Ok((
+        projection(A, side(B, C)?),
+        projection(B, side(C, A)?),
+        projection(C, side(A, B)?),
+    ))
+}
+
+/// The triangle of the excenters.
+pub fn excentral((A, B, C): Trig) -> Result<Trig, FuncError> {
+    let J = |t| excenter(t).map_err(FuncError::CalcError);
+    Ok((J((A, B, C))?, J((B, C, A))?, J((C, A, B))?))
+}
+
+/// The feet of the perpendiculars from `P` to the sides.
+pub fn pedal((A, B, C): Trig, P: Point) -> Result<Trig, FuncError> {
+    Ok((
+        projection(P, side(B, C)?),
+        projection(P, side(C, A)?),
+        projection(P, side(A, B)?),
+    ))
+}
+
+/// The intersections of `AP`, `BP` and `CP` with the opposite sides.
+pub fn cevian(t: Trig, P: Point) -> Result<Trig, FuncError> {
+    let (u, v, w) = barycentric(t, P);
+    Ok((
+        point(t, (0.0, v, w))?,
+        point(t, (u, 0.0, w))?,
+        point(t, (u, v, 0.0))?,
+    ))
+}
+
+/// The triangle with respect to which the given one is the cevian triangle
+/// of `P`.
+pub fn anticevian(t: Trig, P: Point) -> Result<Trig, FuncError> {
+    let (u, v, w) = barycentric(t, P);
+    Ok((
+        point(t, (-u, v, w))?,
+        point(t, (u, -v, w))?,
+        point(t, (u, v, -w))?,
+    ))
+}
@@ -0,0 +1,151 @@
+// Locating interpreter errors in the source, and reporting them the same
+// way `pest` reports syntax errors.
+
+use super::parser::ast::Span;
+use anyhow::Result;
+use pest::{error::InputLocation, RuleType};
+use std::{fmt::Display, path::Path};
+use thiserror::Error;
+
+/// An error raised at a certain span of the source.
+#[derive(Debug, Error)]
+#[error("{error}")]
+pub struct SpannedError {
+    pub span: Span,
+    pub error: anyhow::Error,
+}
+
+impl SpannedError {
+    /// Drop the location of an error, if it has one.
+    #[inline]
+    pub fn strip(error: anyhow::Error) -> anyhow::Error {
+        match error.downcast::<SpannedError>() {
+            Ok(e) => e.error,
+            Err(e) => e,
+        }
+    }
+}
+
+/// Errors of several steps of a single line.
+#[derive(Debug, Error)]
+#[error("{}", .0.iter().map(|e| e.to_string()).collect::<Vec<_>>().join("; "))]
+pub struct Errors(pub Vec<anyhow::Error>);
+
+impl Errors {
+    /// Fail if any error is collected.
+    #[inline]
+    pub fn check(errors: Vec<anyhow::Error>) -> Result<()> {
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(Errors(errors))?
+        }
+    }
+}
+
+/// Attach a span to errors that are not located yet.
+pub(super) trait Locate<T> {
+    fn at(self, span: Span) -> Result<T>;
+}
+
+impl<T> Locate<T> for Result<T> {
+    #[inline]
+    fn at(self, span: Span) -> Result<T> {
+        self.map_err(|error| {
+            if error.is::<SpannedError>() || error.is::<Diagnostic>() || error.is::<Diagnostics>() {
+                error
+            } else {
+                SpannedError { span, error }.into()
+            }
+        })
+    }
+}
+
+/// An error reported with its position and an excerpt of the source.
+#[derive(Debug, Error)]
+pub struct Diagnostic {
+    pub path: Option<String>,
+    pub line: usize,
+    pub col: usize,
+    /// The line of source the error is on.
+    pub excerpt: String,
+    /// Length of the span on this line, in characters.
+    pub len: usize,
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// Locate `error` in `source`. Errors without a span are reported at `span`.
+    pub fn new(error: anyhow::Error, span: Span, source: &str, path: Option<&Path>) -> Self {
+        match error.downcast::<SpannedError>() {
+            Ok(e) => Self::locate(e.error.to_string(), e.span, source, path),
+            Err(e) => Self::locate(e.to_string(), span, source, path),
+        }
+    }
+    /// Report a syntax error the same way as other errors.
+    pub fn from_parse_error<R: RuleType>(
+        error: &pest::error::Error<R>,
+        source: &str,
+        path: Option<&Path>,
+    ) -> Self {
+        let span = match error.location {
+            InputLocation::Pos(pos) => Span {
+                start: pos,
+                end: pos,
+            },
+            InputLocation::Span((start, end)) => Span { start, end },
+        };
+        Self::locate(error.variant.message().to_string(), span, source, path)
+    }
+    fn locate(message: String, span: Span, source: &str, path: Option<&Path>) -> Self {
+        let start = span.start.min(source.len());
+        let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+        let line_end = source[start..]
+            .find('\n')
+            .map_or(source.len(), |i| start + i);
+        let end = span.end.clamp(start, line_end);
+        Diagnostic {
+            path: path.map(|p| p.display().to_string()),
+            line: source[..start].matches('\n').count() + 1,
+            col: source[line_start..start].chars().count() + 1,
+            excerpt: source[line_start..line_end].trim_end().to_string(),
+            len: source[start..end].chars().count(),
+            message,
+        }
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let spacing = " ".repeat(self.line.to_string().len());
+        let underline = match self.len {
+            0 | 1 => String::from("^"),
+            len => format!("^{}^", "-".repeat(len - 2)),
+        };
+        match &self.path {
+            Some(path) => writeln!(f, "{}--> {}:{}:{}", spacing, path, self.line, self.col)?,
+            None => writeln!(f, "{}--> {}:{}", spacing, self.line, self.col)?,
+        }
+        writeln!(f, "{} |", spacing)?;
+        writeln!(f, "{} | {}", self.line, self.excerpt)?;
+        writeln!(f, "{} | {}{}", spacing, " ".repeat(self.col - 1), underline)?;
+        writeln!(f, "{} |", spacing)?;
+        write!(f, "{} = {}", spacing, self.message)
+    }
+}
+
+/// All errors found while interpreting in recovery mode.
+#[derive(Debug, Error)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl Display for Diagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (i, diagnostic) in self.0.iter().enumerate() {
+            if i > 0 {
+                write!(f, "\n\n")?;
+            }
+            write!(f, "{}", diagnostic)?;
+        }
+        Ok(())
+    }
+}
@@ -102,6 +102,17 @@ macro_rules! write_arc {
     };
 }
 
+#[macro_export]
+macro_rules! write_path {
+    ($str:ident, $d:expr, $color:expr, $fill:expr, $width:expr, $dash:expr) => {
+        write!(
+            $str,
+            "<path d=\"{}\" stroke=\"{}\" fill=\"{}\" stroke-width=\"{}\"{}/>",
+            $d, $color, $fill, $width, $dash,
+        )
+    };
+}
+
 #[macro_export]
 macro_rules! write_polygon {
     ($str:ident, $pts:ident, $fill:expr) => {
@@ -27,9 +27,9 @@ impl DecorConfig {
             if let ConfigValue::String(fill) = dobj.get_unchecked("decorfill");
             then {
                 Ok(DecorConfig {
-                    pos: dobj.get_position(*loc),
+                    pos: dobj.get_position(*loc)?,
                     size: *size,
-                    angle: dobj.get_tan_angle(*loc),
+                    angle: dobj.get_tan_angle(*loc)?,
                     width: *width,
                     color: color.clone(),
                     fill: fill.clone(),
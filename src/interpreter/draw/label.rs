@@ -24,7 +24,7 @@ impl StyledDObject<'_> {
         let angle = get_or_wrong_type!(self, "angle")?;
         let loc = get_or_wrong_type!(self, "loc")?;
         let font = self.get_unchecked("font");
-        let pos = self.get_position(loc);
+        let pos = self.get_position(loc)?;
 
         // Process the label.
 
@@ -1,12 +1,14 @@
 use crate::{
     interpreter::{
         draw::CM,
+        interpret::InterpretError,
         parser::ast::Config,
-        structs::{Arc, Segment},
+        structs::{Arc, Path, Segment},
         utils::{ConfigValue, DObject},
     },
-    write_arc, write_circle, write_line, write_polygon, write_polyline,
+    write_arc, write_circle, write_line, write_path, write_polygon, write_polyline,
 };
+use anyhow::Result;
 use if_chain::if_chain;
 use itertools::Itertools;
 use metric_rs::calc::{
@@ -103,6 +105,30 @@ impl Display for StyledDObject<'_> {
                     dash
                 )
             }
+            DObject::Path(Path { pieces, closed }) => {
+                let d = pieces
+                    .iter()
+                    .map(|piece| {
+                        let pts = piece
+                            .iter()
+                            .map(|p| format!("{},{}", p.x * CM, -p.y * CM))
+                            .join(" L ");
+                        if *closed {
+                            format!("M {} Z", pts)
+                        } else {
+                            format!("M {}", pts)
+                        }
+                    })
+                    .join(" ");
+                write_path!(
+                    f,
+                    d,
+                    self.get_unchecked("color"),
+                    self.get_unchecked("fill"),
+                    self.get_unchecked("linewidth"),
+                    dash
+                )
+            }
             DObject::Polygon(poly) => {
                 let pts = poly
                     .iter()
@@ -158,12 +184,15 @@ impl Display for StyledDObject<'_> {
 }
 
 impl StyledDObject<'_> {
-    pub(super) fn get_position(&self, loc: f64) -> Point {
-        match &self.obj {
+    /// Get the point at `loc` along the object. A path with nothing drawn has
+    /// no such point.
+    pub(super) fn get_position(&self, loc: f64) -> Result<Point> {
+        Ok(match &self.obj {
             DObject::Point(p) => *p,
             DObject::Circle(c) => c.point_on(loc),
             DObject::Arc(arc) => arc.point_on(loc),
             DObject::Segment(seg) => seg.point_on(loc),
+            DObject::Path(path) => path.point_on(loc).ok_or(InterpretError::OutOfView)?,
             DObject::Polygon(poly) => center(poly),
             // TODO: Error handling in this branch
             DObject::Angle3P(a, o, b) => {
@@ -178,16 +207,21 @@ impl StyledDObject<'_> {
                 let arc = Arc::from_center(a, o, b).unwrap();
                 arc.point_on(loc)
             }
-        }
+        })
     }
 
     /// Get the angle of the tangent line at a certain point.
-    pub(super) fn get_tan_angle(&self, loc: f64) -> f64 {
-        match &self.obj {
+    pub(super) fn get_tan_angle(&self, loc: f64) -> Result<f64> {
+        Ok(match &self.obj {
             DObject::Segment(seg) => {
                 let Segment { from, to } = seg;
                 (from.y - to.y).atan2(from.x - to.x)
             }
+            DObject::Path(_) => {
+                let from = self.get_position(loc - 1e-3)?;
+                let to = self.get_position(loc + 1e-3)?;
+                (from.y - to.y).atan2(from.x - to.x)
+            }
             DObject::Circle(_) => -(loc + 0.25) * PI * 2.0,
             DObject::Arc(arc) => {
                 let Arc { from, to, O, .. } = arc;
@@ -201,6 +235,6 @@ impl StyledDObject<'_> {
                 loc * end + (1.0 - loc) * start
             }
             DObject::Polygon(_) | DObject::Point(_) => 0.0,
-        }
+        })
     }
 }
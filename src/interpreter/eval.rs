@@ -1,55 +1,451 @@
-use std::f64::consts::{E, PI};
-
-use meval::{ContextProvider, Expr};
+// Evaluation of math expressions between `$`s.
 
 use super::{
     interpret::{InterpretError, InterpreterState},
     utils::GObject,
 };
+use anyhow::Result;
+use metric_rs::objects::Point;
+use std::{
+    f64::consts::{E, PI},
+    iter::Peekable,
+    str::Chars,
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum EvalError {
+    #[error("Unexpected character: {0}")]
+    UnexpectedChar(char),
+    #[error("Unexpected token: {0}")]
+    UnexpectedToken(String),
+    #[error("Unexpected end of expression")]
+    UnexpectedEnd,
+    #[error("Unknown function: {0}")]
+    UnknownFunction(String),
+    #[error("Function {0} takes {1} argument(s), but {2} given")]
+    ArgCount(String, usize, usize),
+    #[error("Function {0} takes at least 1 argument")]
+    NoArgs(String),
+    #[error("Cannot apply {0} to {1}")]
+    Operand(String, String),
+    #[error("Cannot apply {0} to {1} and {2}")]
+    Operands(char, &'static str, &'static str),
+    #[error("No such field: {0}")]
+    NoField(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Name(String),
+    Op(char),
+}
+
+impl Token {
+    #[inline]
+    fn describe(&self) -> String {
+        match self {
+            Self::Number(x) => x.to_string(),
+            Self::Name(s) => s.clone(),
+            Self::Op(c) => c.to_string(),
+        }
+    }
+}
+
+/// Whether `chars` go on with the word `word`, not followed by more
+/// characters of a name.
+#[inline]
+fn ahead_word(chars: &Peekable<Chars>, word: &str) -> bool {
+    let mut ahead = chars.clone();
+    word.chars().all(|c| ahead.next() == Some(c))
+        && !ahead
+            .next()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Split an expression into tokens. A number directly followed by `deg` is
+/// an angle in degrees.
+fn tokenize(expr: &str) -> Result<Vec<Token>, EvalError> {
+    let mut tokens = Vec::new();
+    let mut chars = expr.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.clone().nth(1).is_some_and(|c| c.is_ascii_digit()))
+        {
+            let mut number = String::new();
+            while let Some(c) = chars.next_if(|&c| c.is_ascii_digit() || c == '.') {
+                number.push(c);
+            }
+            // An exponent, like in `1e-3`. Otherwise `e` is a name.
+            let mut ahead = chars.clone();
+            if ahead.next_if(|&c| c == 'e' || c == 'E').is_some() {
+                let sign = ahead.next_if(|&c| c == '+' || c == '-');
+                if ahead.peek().is_some_and(char::is_ascii_digit) {
+                    number.push('e');
+                    number.extend(sign);
+                    chars = ahead;
+                    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+                        number.push(c);
+                    }
+                }
+            }
+            let mut x: f64 = number
+                .parse()
+                .map_err(|_| EvalError::UnexpectedToken(number))?;
+            if ahead_word(&chars, "deg") {
+                x = x.to_radians();
+                chars.nth(2);
+            }
+            tokens.push(Token::Number(x));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek() {
+                if c.is_ascii_alphanumeric() || c == '_' {
+                    name.push(c);
+                    chars.next();
+                } else {
+                    break;
+                }
+            }
+            if chars.peek() == Some(&'\'') {
+                name.push('\'');
+                chars.next();
+            }
+            tokens.push(Token::Name(name));
+        } else if "+-*/^(),|.".contains(c) {
+            tokens.push(Token::Op(c));
+            chars.next();
+        } else {
+            Err(EvalError::UnexpectedChar(c))?
+        }
+    }
+    Ok(tokens)
+}
+
+/// Parsed math expressions.
+#[derive(Debug)]
+enum Expr {
+    Number(f64),
+    Name(String),
+    Neg(Box<Expr>),
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(String, Vec<Expr>),
+    /// Absolute value of a number, or length of a vector.
+    Abs(Box<Expr>),
+    /// A coordinate of a point, like `A.x`.
+    Field(Box<Expr>, String),
+    /// A point given by its coordinates, like `(1, 2)`.
+    Pair(Box<Expr>, Box<Expr>),
+}
+
+/// A recursive descent parser of expressions, from lowest precedence to highest:
+/// `+ -`, `* /`, unary `-`, `^` (right-associative), `.x`/`.y`.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    #[inline]
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+    #[inline]
+    fn next(&mut self) -> Result<Token, EvalError> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token.ok_or(EvalError::UnexpectedEnd)
+    }
+    #[inline]
+    fn eat(&mut self, op: char) -> bool {
+        if self.peek() == Some(&Token::Op(op)) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+    #[inline]
+    fn expect(&mut self, op: char) -> Result<(), EvalError> {
+        match self.next()? {
+            Token::Op(c) if c == op => Ok(()),
+            token => Err(EvalError::UnexpectedToken(token.describe())),
+        }
+    }
+    fn parse(mut self) -> Result<Expr, EvalError> {
+        let expr = self.sum()?;
+        match self.peek() {
+            None => Ok(expr),
+            Some(token) => Err(EvalError::UnexpectedToken(token.describe())),
+        }
+    }
+    fn sum(&mut self) -> Result<Expr, EvalError> {
+        let mut lhs = self.product()?;
+        while let Some(&Token::Op(op @ ('+' | '-'))) = self.peek() {
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.product()?));
+        }
+        Ok(lhs)
+    }
+    fn product(&mut self) -> Result<Expr, EvalError> {
+        let mut lhs = self.unary()?;
+        while let Some(&Token::Op(op @ ('*' | '/'))) = self.peek() {
+            self.pos += 1;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(self.unary()?));
+        }
+        Ok(lhs)
+    }
+    fn unary(&mut self) -> Result<Expr, EvalError> {
+        if self.eat('-') {
+            Ok(Expr::Neg(Box::new(self.unary()?)))
+        } else if self.eat('+') {
+            self.unary()
+        } else {
+            self.power()
+        }
+    }
+    fn power(&mut self) -> Result<Expr, EvalError> {
+        let base = self.field()?;
+        if self.eat('^') {
+            Ok(Expr::Binary('^', Box::new(base), Box::new(self.unary()?)))
+        } else {
+            Ok(base)
+        }
+    }
+    fn field(&mut self) -> Result<Expr, EvalError> {
+        let mut expr = self.atom()?;
+        while self.eat('.') {
+            match self.next()? {
+                Token::Name(field) => expr = Expr::Field(Box::new(expr), field),
+                token => Err(EvalError::UnexpectedToken(token.describe()))?,
+            }
+        }
+        Ok(expr)
+    }
+    fn atom(&mut self) -> Result<Expr, EvalError> {
+        match self.next()? {
+            Token::Number(x) => Ok(Expr::Number(x)),
+            Token::Name(name) => {
+                if !self.eat('(') {
+                    return Ok(Expr::Name(name));
+                }
+                let mut args = Vec::new();
+                if !self.eat(')') {
+                    loop {
+                        args.push(self.sum()?);
+                        if self.eat(')') {
+                            break;
+                        }
+                        self.expect(',')?;
+                    }
+                }
+                Ok(Expr::Call(name, args))
+            }
+            Token::Op('(') => {
+                let expr = self.sum()?;
+                if self.eat(',') {
+                    let y = self.sum()?;
+                    self.expect(')')?;
+                    return Ok(Expr::Pair(Box::new(expr), Box::new(y)));
+                }
+                self.expect(')')?;
+                Ok(expr)
+            }
+            Token::Op('|') => {
+                let expr = self.sum()?;
+                self.expect('|')?;
+                Ok(Expr::Abs(Box::new(expr)))
+            }
+            token => Err(EvalError::UnexpectedToken(token.describe())),
+        }
+    }
+}
+
+/// Get the number of an argument of `name`.
+#[inline]
+fn number(name: &str, x: GObject) -> Result<f64, EvalError> {
+    match x {
+        GObject::Number(x) => Ok(x),
+        x => Err(EvalError::Operand(name.to_string(), x.kind().to_string())),
+    }
+}
+
+#[inline]
+fn norm(p: Point) -> f64 {
+    p.x.hypot(p.y)
+}
 
 macro_rules! func {
-    ($args:ident; $len:literal, $ret:expr) => {
-        if $args.len() < $len {
-            Err(meval::FuncEvalError::TooFewArguments)
+    ($name:ident, $args:ident; [$($x:ident),+] => $ret:expr) => {
+        if let [$($x),+] = $args[..] {
+            $(let $x = number($name, $x)?;)+
+            Ok(GObject::Number($ret))
         } else {
-            Ok($ret)
+            Err(EvalError::ArgCount(
+                $name.to_string(),
+                [$(stringify!($x)),+].len(),
+                $args.len(),
+            ))?
         }
     };
 }
 
-impl ContextProvider for InterpreterState {
+impl InterpreterState {
+    /// Evaluate a math expression, resulting in a number or a point. Names
+    /// refer to numeric objects and points, which act as vectors, or else to
+    /// the constants `pi`, `e` and `deg`.
     #[inline]
-    fn get_var(&self, key: &str) -> Option<f64> {
-        match key {
-            "pi" => Some(PI),
-            "e" => Some(E),
-            "deg" => Some(PI / 180.0),
-            _ => {
-                if let Some(GObject::Number(x)) = self.get(key) {
-                    Some(*x)
-                } else {
-                    None
-                }
+    pub(super) fn eval(&self, expr: &str) -> Result<GObject> {
+        let tokens = tokenize(expr)?;
+        let expr = Parser { tokens, pos: 0 }.parse()?;
+        self.eval_expr(&expr)
+    }
+    fn eval_expr(&self, expr: &Expr) -> Result<GObject> {
+        match expr {
+            Expr::Number(x) => Ok(GObject::Number(*x)),
+            Expr::Name(name) => self.eval_name(name),
+            Expr::Neg(x) => match self.eval_expr(x)? {
+                GObject::Number(x) => Ok(GObject::Number(-x)),
+                GObject::Point(p) => Ok(GObject::Point(Point { x: -p.x, y: -p.y })),
+                x => Err(EvalError::Operand(String::from("-"), x.kind().to_string()))?,
+            },
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.eval_expr(lhs)?;
+                let rhs = self.eval_expr(rhs)?;
+                Ok(binary(*op, lhs, rhs)?)
+            }
+            Expr::Call(name, args) => {
+                let args = args
+                    .iter()
+                    .map(|x| self.eval_expr(x))
+                    .collect::<Result<Vec<_>>>()?;
+                self.eval_func(name, &args)
             }
+            Expr::Abs(x) => match self.eval_expr(x)? {
+                GObject::Number(x) => Ok(GObject::Number(x.abs())),
+                GObject::Point(p) => Ok(GObject::Number(norm(p))),
+                x => Err(EvalError::Operand(
+                    String::from("|...|"),
+                    x.kind().to_string(),
+                ))?,
+            },
+            Expr::Field(x, field) => match (self.eval_expr(x)?, field.as_str()) {
+                (GObject::Point(p), "x") => Ok(GObject::Number(p.x)),
+                (GObject::Point(p), "y") => Ok(GObject::Number(p.y)),
+                (GObject::Point(_), _) => Err(EvalError::NoField(field.clone()))?,
+                (x, _) => Err(EvalError::Operand(
+                    format!(".{}", field),
+                    x.kind().to_string(),
+                ))?,
+            },
+            Expr::Pair(x, y) => Ok(GObject::Point(Point {
+                x: number("(x, y)", self.eval_expr(x)?)?,
+                y: number("(x, y)", self.eval_expr(y)?)?,
+            })),
         }
     }
+    /// Get the value of a name. Objects named like the constants take
+    /// precedence over them.
     #[inline]
-    fn eval_func(&self, name: &str, args: &[f64]) -> Result<f64, meval::FuncEvalError> {
+    fn eval_name(&self, name: &str) -> Result<GObject> {
+        match (self.get(name), name) {
+            (Some(x @ (GObject::Number(_) | GObject::Point(_))), _) => Ok(*x),
+            (Some(obj), _) => Err(InterpretError::WrongGeometricType(
+                "Number or Point",
+                obj.kind(),
+            ))?,
+            (None, "pi") => Ok(GObject::Number(PI)),
+            (None, "e") => Ok(GObject::Number(E)),
+            (None, "deg") => Ok(GObject::Number(PI / 180.0)),
+            (None, _) => Err(self.missing(name.to_string()))?,
+        }
+    }
+    #[inline]
+    fn eval_func(&self, name: &str, args: &[GObject]) -> Result<GObject> {
+        match (name, args) {
+            ("abs", [GObject::Point(p)]) => return Ok(GObject::Number(norm(*p))),
+            ("dot", [GObject::Point(u), GObject::Point(v)]) => {
+                return Ok(GObject::Number(u.x * v.x + u.y * v.y))
+            }
+            ("cross", [GObject::Point(u), GObject::Point(v)]) => {
+                return Ok(GObject::Number(u.x * v.y - u.y * v.x))
+            }
+            ("dot" | "cross", _) => Err(EvalError::Operand(
+                name.to_string(),
+                args.iter()
+                    .map(GObject::kind)
+                    .collect::<Vec<_>>()
+                    .join(", "),
+            ))?,
+            _ => {}
+        }
         match name {
-            "sqrt" => func!(args; 1, args[0].sqrt()),
-            "sin" => func!(args; 1, args[0].sin()),
-            "cos" => func!(args; 1, args[0].cos()),
-            "tan" => func!(args; 1, args[0].tan()),
-            _ => Err(meval::FuncEvalError::UnknownFunction),
+            "sqrt" => func!(name, args; [x] => x.sqrt()),
+            "sin" => func!(name, args; [x] => x.sin()),
+            "cos" => func!(name, args; [x] => x.cos()),
+            "tan" => func!(name, args; [x] => x.tan()),
+            "asin" => func!(name, args; [x] => x.asin()),
+            "acos" => func!(name, args; [x] => x.acos()),
+            "atan" => func!(name, args; [x] => x.atan()),
+            "atan2" => func!(name, args; [y, x] => y.atan2(x)),
+            "abs" => func!(name, args; [x] => x.abs()),
+            "signum" => func!(name, args; [x] => x.signum()),
+            "pow" => func!(name, args; [x, y] => x.powf(y)),
+            "exp" => func!(name, args; [x] => x.exp()),
+            "ln" => func!(name, args; [x] => x.ln()),
+            "floor" => func!(name, args; [x] => x.floor()),
+            "ceil" => func!(name, args; [x] => x.ceil()),
+            "round" => func!(name, args; [x] => x.round()),
+            "min" | "max" if args.is_empty() => Err(EvalError::NoArgs(name.to_string()))?,
+            "min" | "max" => {
+                let args = args
+                    .iter()
+                    .map(|x| number(name, *x))
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(GObject::Number(if name == "min" {
+                    args.into_iter().fold(f64::INFINITY, f64::min)
+                } else {
+                    args.into_iter().fold(f64::NEG_INFINITY, f64::max)
+                }))
+            }
+            _ => Err(EvalError::UnknownFunction(name.to_string()))?,
         }
     }
 }
 
-impl InterpreterState {
-    #[inline]
-    pub(super) fn eval(&self, expr: &str) -> Result<f64, InterpretError> {
-        let expr: Expr = expr.parse()?;
-        let result = expr.eval_with_context(self)?;
-        Ok(result)
+/// Apply a binary operator. Points act as vectors: they can be added,
+/// subtracted, and scaled by numbers.
+#[inline]
+fn binary(op: char, lhs: GObject, rhs: GObject) -> Result<GObject, EvalError> {
+    match (op, lhs, rhs) {
+        (_, GObject::Number(a), GObject::Number(b)) => Ok(GObject::Number(match op {
+            '+' => a + b,
+            '-' => a - b,
+            '*' => a * b,
+            '/' => a / b,
+            '^' => a.powf(b),
+            _ => unreachable!(),
+        })),
+        ('+', GObject::Point(p), GObject::Point(q)) => Ok(GObject::Point(Point {
+            x: p.x + q.x,
+            y: p.y + q.y,
+        })),
+        ('-', GObject::Point(p), GObject::Point(q)) => Ok(GObject::Point(Point {
+            x: p.x - q.x,
+            y: p.y - q.y,
+        })),
+        ('*', GObject::Number(k), GObject::Point(p))
+        | ('*', GObject::Point(p), GObject::Number(k)) => Ok(GObject::Point(Point {
+            x: k * p.x,
+            y: k * p.y,
+        })),
+        ('/', GObject::Point(p), GObject::Number(k)) => Ok(GObject::Point(Point {
+            x: p.x / k,
+            y: p.y / k,
+        })),
+        (op, lhs, rhs) => Err(EvalError::Operands(op, lhs.kind(), rhs.kind())),
     }
 }
@@ -0,0 +1,413 @@
+// Dependency graph of declarations, so that re-binding a free object
+// recomputes everything constructed from it, and draws it again.
+
+use super::{
+    diagnostic::SpannedError,
+    interpret::{InterpretError, InterpreterState, LayerType},
+    parser::ast::*,
+    utils::GObject,
+};
+use anyhow::Result;
+use itertools::Itertools;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// A declaration recorded in the dependency graph.
+#[derive(Debug)]
+pub(super) struct DeclNode {
+    pub left: DeclLeft,
+    pub right: DeclRight,
+    /// Names read by `right`, together with the node each of them was bound by.
+    pub reads: Vec<(String, usize)>,
+    /// The last computed values of `right`.
+    pub value: Vec<GObject>,
+    /// Nodes reading this one.
+    pub dependents: Vec<usize>,
+    /// Drawings reading this one.
+    pub drawings: Vec<usize>,
+}
+
+impl DeclNode {
+    #[inline]
+    pub fn new(
+        left: DeclLeft,
+        right: DeclRight,
+        reads: Vec<(String, usize)>,
+        value: Vec<GObject>,
+    ) -> Self {
+        Self {
+            left,
+            right,
+            reads,
+            value,
+            dependents: Vec::new(),
+            drawings: Vec::new(),
+        }
+    }
+    /// Get the value this node binds to `name`, if any.
+    #[inline]
+    fn output(&self, name: &str) -> Option<GObject> {
+        bind(&self.left, &self.value)
+            .ok()?
+            .into_iter()
+            .find_map(|(x, value)| (x == name).then_some(value))
+    }
+    /// Names bound by this node.
+    #[inline]
+    fn targets(&self) -> Vec<&String> {
+        targets(&self.left)
+    }
+    /// A node is free if it does not depend on any other object.
+    #[inline]
+    fn is_free(&self) -> bool {
+        self.reads.is_empty() && matches!(self.left, DeclLeft::Direct(_))
+    }
+}
+
+/// A `draw` or `decor` step.
+#[derive(Debug, Clone)]
+pub(super) enum Step {
+    Draw(StyledObject),
+    Decor(DecorObject),
+}
+
+impl Step {
+    #[inline]
+    pub fn obj(&self) -> &Object {
+        match self {
+            Step::Draw(step) => &step.obj,
+            Step::Decor(step) => &step.obj,
+        }
+    }
+}
+
+/// SVG code emitted by a step, with its layer.
+pub(super) type Output = Vec<(LayerType, String)>;
+
+/// A step drawn so far, kept so that it can be drawn again when an object it
+/// reads is re-bound.
+#[derive(Debug)]
+pub(super) struct Drawing {
+    pub step: Step,
+    /// The configuration in effect when it was drawn.
+    pub config: Config,
+    /// Names read by `step`, together with the node each of them was bound by.
+    pub reads: Vec<(String, usize)>,
+    pub output: Output,
+}
+
+/// Names bound by a declaration.
+#[inline]
+fn targets(left: &DeclLeft) -> Vec<&String> {
+    match left {
+        DeclLeft::Direct(x) => vec![x],
+        DeclLeft::Destruct(names) => names.iter().filter(|x| *x != "_").collect(),
+    }
+}
+
+/// Pair the names bound by a declaration with their values. A triangle is
+/// destructured into its vertices.
+pub(super) fn bind(
+    left: &DeclLeft,
+    value: &[GObject],
+) -> Result<Vec<(String, GObject)>, InterpretError> {
+    let names = match (left, value) {
+        (DeclLeft::Direct(x), [first, ..]) => return Ok(vec![(x.clone(), *first)]),
+        (DeclLeft::Direct(_), []) => return Err(InterpretError::DestructError(0, 1)),
+        (DeclLeft::Destruct(names), _) => names,
+    };
+    let values = match value {
+        [GObject::Trig((a, b, c))] => {
+            vec![GObject::Point(*a), GObject::Point(*b), GObject::Point(*c)]
+        }
+        _ => value.to_vec(),
+    };
+    // Underscores at the end may go without a value, like the second
+    // intersection with a ray when only one is on it.
+    let needed = names.len() - names.iter().rev().take_while(|x| *x == "_").count();
+    if values.len() < needed || values.len() > names.len() {
+        return Err(InterpretError::DestructError(values.len(), names.len()));
+    }
+    Ok(names
+        .iter()
+        .zip(values)
+        .filter(|(x, _)| *x != "_")
+        .map(|(x, value)| (x.clone(), value))
+        .collect())
+}
+
+/// Collect every identifier-like word in an expression. Those not bound to an
+/// object (like function names) are dropped later.
+#[inline]
+fn eval_names(expr: &str, names: &mut Vec<String>) {
+    names.extend(
+        expr.split(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '\''))
+            .filter(|s| s.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_'))
+            .map(String::from),
+    )
+}
+
+/// Collect the names an AST node reads.
+pub(super) trait ReadNames {
+    fn read_names(&self, names: &mut Vec<String>);
+}
+
+impl ReadNames for Linear {
+    fn read_names(&self, names: &mut Vec<String>) {
+        match self {
+            Linear::Line2P(a, b) => names.extend([a.clone(), b.clone()]),
+            Linear::Name(a) => names.push(a.clone()),
+        }
+    }
+}
+
+impl ReadNames for Numeric {
+    fn read_names(&self, names: &mut Vec<String>) {
+        match self {
+            Numeric::Distance2P(a, b) => names.extend([a.clone(), b.clone()]),
+            Numeric::DistancePL(a, l) => {
+                names.push(a.clone());
+                l.read_names(names);
+            }
+            Numeric::Distance2L(k, l) | Numeric::Angle2L(k, l) => {
+                k.read_names(names);
+                l.read_names(names);
+            }
+            Numeric::Angle3P(a, b, c) => names.extend([a.clone(), b.clone(), c.clone()]),
+            Numeric::Number(_) => {}
+            Numeric::Name(a) => names.push(a.clone()),
+            Numeric::Eval(expr) => eval_names(expr, names),
+        }
+    }
+}
+
+impl ReadNames for PointExpr {
+    fn read_names(&self, names: &mut Vec<String>) {
+        match self {
+            PointExpr::Name(a) => names.push(a.clone()),
+            PointExpr::Call(obj) => obj.read_names(names),
+        }
+    }
+}
+
+impl ReadNames for Object {
+    fn read_names(&self, names: &mut Vec<String>) {
+        match self {
+            Object::Line2P(a, b) | Object::Ray(a, b) | Object::CircDiam(a, b) => {
+                names.extend([a.clone(), b.clone()])
+            }
+            Object::CircOA(a, b) => {
+                a.read_names(names);
+                b.read_names(names);
+            }
+            Object::Circ3P(a, b, c)
+            | Object::Arc(a, b, c)
+            | Object::ArcO(a, b, c)
+            | Object::Angle3P(a, b, c)
+            | Object::Triangle(a, b, c) => names.extend([a.clone(), b.clone(), c.clone()]),
+            Object::CircOr(a, r) => {
+                a.read_names(names);
+                r.read_names(names);
+            }
+            Object::Polygon(p) => names.extend(p.iter().cloned()),
+            Object::Name(a) => names.push(a.clone()),
+            Object::Numeric(n) => n.read_names(names),
+            Object::Eval(expr) => eval_names(expr, names),
+            Object::Axis(_) => {}
+            Object::Keyword(_, value) => value.read_names(names),
+            Object::Call(right, _) => right.read_names(names),
+        }
+    }
+}
+
+impl ReadNames for DeclRight {
+    fn read_names(&self, names: &mut Vec<String>) {
+        match self {
+            DeclRight::OrthoCoord(x, y) | DeclRight::PolarCoord(x, y) => {
+                x.read_names(names);
+                y.read_names(names);
+            }
+            DeclRight::Expr(_, args) => args.iter().for_each(|arg| arg.read_names(names)),
+            DeclRight::Object(obj) => obj.read_names(names),
+        }
+    }
+}
+
+impl InterpreterState {
+    /// Resolve the names read by `right` to the nodes currently binding them.
+    #[inline]
+    pub(super) fn reads(&self, right: &impl ReadNames) -> Vec<(String, usize)> {
+        let mut names = Vec::new();
+        right.read_names(&mut names);
+        names.sort();
+        names.dedup();
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let id = *self.bindings.get(&name)?;
+                Some((name, id))
+            })
+            .collect()
+    }
+    /// If `name` is currently bound by a free declaration of a value of the
+    /// same kind as `value`, get its node.
+    #[inline]
+    pub(super) fn free_binding(&self, name: &str, value: &[GObject]) -> Option<usize> {
+        let id = *self.bindings.get(name)?;
+        let node = &self.nodes[id];
+        let same_kind = match (node.value.first(), value.first()) {
+            (Some(old), Some(new)) => std::mem::discriminant(old) == std::mem::discriminant(new),
+            _ => false,
+        };
+        if node.is_free() && same_kind {
+            Some(id)
+        } else {
+            None
+        }
+    }
+    /// Record an interpreted declaration and bind its targets to it.
+    #[inline]
+    pub(super) fn record(&mut self, node: DeclNode) {
+        let id = self.nodes.len();
+        for name in node.targets() {
+            self.bindings.insert(name.clone(), id);
+            self.poisoned.remove(name);
+        }
+        for (_, src) in &node.reads {
+            self.nodes[*src].dependents.push(id);
+        }
+        self.nodes.push(node);
+    }
+    /// Keep a drawn step, so that it is drawn again when a node it reads is
+    /// re-bound.
+    #[inline]
+    pub(super) fn record_step(&mut self, drawing: Drawing) {
+        let id = self.drawings.len();
+        for (_, src) in &drawing.reads {
+            self.nodes[*src].drawings.push(id);
+        }
+        self.drawings.push(drawing);
+    }
+    /// Unbind the targets of a failed declaration, so that objects depending on
+    /// them fail too.
+    #[inline]
+    pub(super) fn poison(&mut self, left: &DeclLeft) {
+        for name in targets(left) {
+            self.objects.remove(name);
+            self.bindings.remove(name);
+            self.poisoned.insert(name.clone());
+        }
+    }
+    /// Replace the definition of the free node `id` with `right`, whose value is
+    /// `value`, recompute every declaration depending on it, in the order they
+    /// were declared, and draw again every step depending on it.
+    ///
+    /// If any dependent fails to compute, nothing is changed.
+    pub(super) fn rebind(
+        &mut self,
+        id: usize,
+        right: DeclRight,
+        value: Vec<GObject>,
+    ) -> Result<()> {
+        let mut backup = vec![(id, std::mem::replace(&mut self.nodes[id].value, value))];
+        let old_right = std::mem::replace(&mut self.nodes[id].right, right);
+        let mut dirty = HashSet::from([id]);
+        let result = self.update(id, &mut dirty, &mut backup);
+        let outputs = match result {
+            Ok(outputs) => outputs,
+            Err(e) => {
+                for (n, value) in backup {
+                    self.nodes[n].value = value;
+                }
+                self.nodes[id].right = old_right;
+                return Err(e);
+            }
+        };
+        for (d, output) in outputs {
+            self.drawings[d].output = output;
+        }
+        // Publish the new values of names that are still bound by these nodes.
+        for n in dirty {
+            let node = &self.nodes[n];
+            for name in node.targets() {
+                if self.bindings.get(name) == Some(&n) {
+                    if let Some(value) = node.output(name) {
+                        self.objects.insert(name.clone(), value);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    /// Recompute the nodes depending on node `id`, directly or not, marking them
+    /// dirty and keeping their old values in `backup`. Then draw again the
+    /// steps depending on a dirty node, and return their new output.
+    fn update(
+        &mut self,
+        id: usize,
+        dirty: &mut HashSet<usize>,
+        backup: &mut Vec<(usize, Vec<GObject>)>,
+    ) -> Result<Vec<(usize, Output)>> {
+        // Nodes only read nodes declared before them, so taking the pending
+        // nodes in declaration order is a topological order.
+        let mut pending: BTreeSet<usize> = self.nodes[id].dependents.iter().copied().collect();
+        let mut steps: BTreeSet<usize> = self.nodes[id].drawings.iter().copied().collect();
+        while let Some(n) = pending.pop_first() {
+            // The span of an error is in the dependent declaration, not the one
+            // being interpreted.
+            let value = self.recompute(n).map_err(|e| {
+                let names = self.nodes[n].targets().into_iter().join(", ");
+                InterpretError::UpdateError(names, SpannedError::strip(e))
+            })?;
+            backup.push((n, std::mem::replace(&mut self.nodes[n].value, value)));
+            dirty.insert(n);
+            pending.extend(&self.nodes[n].dependents);
+            steps.extend(&self.nodes[n].drawings);
+        }
+        let mut outputs = Vec::new();
+        for d in steps {
+            let output = self.redraw(d).map_err(|e| {
+                let names = self.drawings[d]
+                    .reads
+                    .iter()
+                    .map(|(name, _)| name)
+                    .join(", ");
+                InterpretError::UpdateError(format!("drawing of {names}"), SpannedError::strip(e))
+            })?;
+            outputs.push((d, output));
+        }
+        Ok(outputs)
+    }
+    /// The names read by `reads`, each bound to its value in the node it was
+    /// originally read from.
+    #[inline]
+    fn scope(&self, reads: &[(String, usize)]) -> HashMap<String, GObject> {
+        reads
+            .iter()
+            .filter_map(|(name, src)| Some((name.clone(), self.nodes[*src].output(name)?)))
+            .collect()
+    }
+    /// Evaluate node `n` again, seeing each name it reads as bound by the node it
+    /// originally read it from.
+    fn recompute(&mut self, n: usize) -> Result<Vec<GObject>> {
+        let scope = self.scope(&self.nodes[n].reads);
+        let right = self.nodes[n].right.clone();
+        let objects = std::mem::replace(&mut self.objects, scope);
+        let result = self.decl_right(right);
+        self.objects = objects;
+        let value = result?;
+        bind(&self.nodes[n].left, &value)?;
+        Ok(value)
+    }
+    /// Draw step `d` again, with the objects it reads and the configuration it
+    /// was drawn with.
+    fn redraw(&mut self, d: usize) -> Result<Output> {
+        let scope = self.scope(&self.drawings[d].reads);
+        let objects = std::mem::replace(&mut self.objects, scope);
+        let config = std::mem::replace(&mut self.config, self.drawings[d].config.clone());
+        let step = self.drawings[d].step.clone();
+        let result = self.render(&step);
+        self.objects = objects;
+        self.config = config;
+        result
+    }
+}
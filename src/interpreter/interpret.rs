@@ -1,10 +1,13 @@
 use super::structs::{Arc, Segment};
 use super::{
-    builtin::{config::DEFAULT_CONFIG, functions::FUNCTIONS},
+    builtin::{config::DEFAULT_CONFIG, functions::FUNCTIONS, predicates::PREDICATES},
+    diagnostic::{Diagnostic, Diagnostics, Errors, Locate},
     draw::render::StyledDObject,
+    graph::{bind, DeclNode, Drawing, Output, Step},
+    loops::Substitute,
     parser::ast::*,
     parser::parse,
-    utils::{ConfigValue, DObject, GObject},
+    utils::{ConfigValue, DObject, GObject, Keywords},
 };
 use crate::interpreter::{draw::CM, utils::FuncError};
 use anyhow::Result;
@@ -18,12 +21,17 @@ use metric_rs::{
 };
 #[cfg(test)]
 use serde::Serialize;
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 use thiserror::Error;
 
 /// Types of layers.
 #[cfg_attr(test, derive(Serialize))]
-#[derive(Debug, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum LayerType {
     Dots,
     Lines,
@@ -56,32 +64,83 @@ pub enum InterpretError {
     ParseError(String),
     #[error("Name not defined: {0}")]
     MissingKey(String),
-    #[error("Wrong geometric type")]
-    WrongGeometricType,
+    #[error("Wrong geometric type: expected {0}, found {1}")]
+    WrongGeometricType(&'static str, &'static str),
     #[error("Wrong configuration type")]
     WrongConfigType,
     #[error("No such decoration")]
     NoSuchDecor,
-    #[error("Evaluation error: {0}")]
-    EvalError(meval::Error),
+    #[error("Cannot update {0}: {1}")]
+    UpdateError(String, anyhow::Error),
+    #[error("Depends on failed {0}")]
+    Poisoned(String),
+    #[error("Cannot read {0}: {1}")]
+    ReadError(String, std::io::Error),
+    #[error("Circular include of {0}")]
+    CircularInclude(String),
+    #[error("No such predicate: {0}")]
+    NoPredicate(String),
+    #[error("Assertion failed: {0} (residual {1:.1e})")]
+    AssertionFailed(String, f64),
+    #[error("Line does not cross the picture")]
+    OutOfView,
+    #[error("Cannot destructure {0} values into {1} names")]
+    DestructError(usize, usize),
+    #[error("Unexpected keyword argument: {0}")]
+    UnexpectedKeyword(String),
+    #[error("Cannot pick value {0} out of {1}")]
+    PickError(usize, usize),
 }
 
 /// Represents the state of an interpreter.
 #[cfg_attr(test, derive(Serialize))]
 #[derive(Debug)]
 pub struct InterpreterState {
-    objects: HashMap<String, GObject>,
+    pub(super) objects: HashMap<String, GObject>,
+    pub(super) config: Config,
+    /// Every declaration interpreted so far, in order.
+    #[cfg_attr(test, serde(skip))]
+    pub(super) nodes: Vec<DeclNode>,
+    /// The node each name is currently bound by.
+    #[cfg_attr(test, serde(skip))]
+    pub(super) bindings: HashMap<String, usize>,
+    /// Names whose last declaration failed.
+    #[cfg_attr(test, serde(skip))]
+    pub(super) poisoned: HashSet<String>,
+    /// Keep interpreting after an error.
+    #[cfg_attr(test, serde(skip))]
+    pub(super) recover: bool,
+    /// Macros defined with `def`.
+    #[cfg_attr(test, serde(skip))]
+    pub(super) macros: HashMap<String, Rc<Macro>>,
+    /// Names of the macros being called.
+    #[cfg_attr(test, serde(skip))]
+    pub(super) calling: Vec<String>,
+    /// Paths of the files being interpreted, the innermost include last.
+    #[cfg_attr(test, serde(skip))]
+    files: Vec<PathBuf>,
+    /// Every step drawn so far, in order.
     #[cfg_attr(test, serde(skip))]
-    layer: Layer,
-    config: Config,
+    pub(super) drawings: Vec<Drawing>,
 }
 
-/// Convenience macro to get a value or fail with `MissingKey`.
+/// Convenience macro to get a value or fail with `MissingKey`,
+/// or `Poisoned` if the declaration of this name failed.
 macro_rules! get {
-    ($objs:expr , $key:ident) => {
-        $objs
+    ($self:ident, $key:ident) => {
+        $self
+            .objects
             .get(&$key)
-            .ok_or_else(|| InterpretError::MissingKey($key))?
+            .ok_or_else(|| $self.missing($key))?
+    };
+    ($self:ident, $key:ident, $kind:ident) => {
+        match get!($self, $key) {
+            GObject::$kind(x) => *x,
+            obj => Err(InterpretError::WrongGeometricType(
+                stringify!($kind),
+                obj.kind(),
+            ))?,
+        }
     };
 }
 
@@ -98,15 +157,35 @@ impl InterpreterState {
     pub fn new() -> Self {
         InterpreterState {
             objects: HashMap::new(),
-            layer: Layer(HashMap::new()),
             config: DEFAULT_CONFIG.clone(),
+            nodes: Vec::new(),
+            bindings: HashMap::new(),
+            poisoned: HashSet::new(),
+            recover: false,
+            macros: HashMap::new(),
+            calling: Vec::new(),
+            files: Vec::new(),
+            drawings: Vec::new(),
         }
     }
     #[inline]
     pub fn clear(&mut self) {
         self.objects.clear();
-        self.layer.0.clear();
         self.config.clone_from(&DEFAULT_CONFIG);
+        self.nodes.clear();
+        self.bindings.clear();
+        self.poisoned.clear();
+        self.macros.clear();
+        self.drawings.clear();
+    }
+    /// Set whether to keep interpreting after an error.
+    ///
+    /// In this mode, a declaration that fails makes every object depending on it
+    /// fail too, and the other objects are still drawn, so `emit` gives a partial
+    /// figure. All errors are returned together as [`Diagnostics`].
+    #[inline]
+    pub fn set_recover(&mut self, recover: bool) {
+        self.recover = recover;
     }
 
     // Object getter.
@@ -116,30 +195,115 @@ impl InterpreterState {
     }
 
     // Main functions.
+    /// Interpret some source code.
+    ///
+    /// The state is kept between calls, so this can also be used to re-bind
+    /// objects of a figure already interpreted.
     #[inline]
     pub fn interpret(&mut self, source: &str) -> Result<()> {
+        self.interpret_source(source, None)
+    }
+    /// Interpret the file at `path`. Errors are reported with this path.
+    #[inline]
+    pub fn interpret_file(&mut self, path: &Path) -> Result<()> {
+        let source = fs::read_to_string(path)?;
+        self.files.push(path.to_path_buf());
+        let result = self.interpret_source(&source, Some(path));
+        self.files.pop();
+        result
+    }
+    #[inline]
+    fn interpret_source(&mut self, source: &str, path: Option<&Path>) -> Result<()> {
         let input = parse(source);
         match input {
-            Err(e) => Err(InterpretError::ParseError(e.to_string()))?,
-            Ok(input) => {
-                for line in input {
-                    if let Err(e) = self._interpret(line) {
-                        return Err(e)?;
-                    }
+            Err(e) => {
+                let e = match path {
+                    Some(path) => e.with_path(&path.display().to_string()),
+                    None => e,
+                };
+                Err(InterpretError::ParseError(e.to_string()))?
+            }
+            Ok(input) => self.interpret_main(input, source, path),
+        }
+    }
+    #[inline]
+    fn interpret_main(&mut self, input: Main, source: &str, path: Option<&Path>) -> Result<()> {
+        let mut diagnostics = Vec::new();
+        for Spanned { inner, span } in input {
+            if let Err(e) = self._interpret(inner) {
+                let errors = match e.downcast::<Errors>() {
+                    Ok(Errors(errors)) => errors,
+                    Err(e) => vec![e],
+                };
+                for e in errors {
+                    // Errors in included files are already located.
+                    let e = match e.downcast::<Diagnostics>() {
+                        Ok(Diagnostics(included)) => {
+                            diagnostics.extend(included);
+                            continue;
+                        }
+                        Err(e) => e,
+                    };
+                    diagnostics.push(match e.downcast::<Diagnostic>() {
+                        Ok(diagnostic) => diagnostic,
+                        Err(e) => Diagnostic::new(e, span, source, path),
+                    });
+                }
+                if !self.recover {
+                    break;
                 }
-                Ok(())
+            }
+        }
+        match diagnostics.pop() {
+            None => Ok(()),
+            Some(diagnostic) if !self.recover => Err(diagnostic)?,
+            Some(diagnostic) => {
+                diagnostics.push(diagnostic);
+                Err(Diagnostics(diagnostics))?
             }
         }
     }
     #[inline]
-    fn _interpret(&mut self, input: FileLine) -> Result<()> {
+    pub(super) fn _interpret(&mut self, mut input: FileLine) -> Result<()> {
+        input.substitute(self)?;
         match input {
             FileLine::Config(config) => self.config(config),
             FileLine::Decl(decl) => self.decl(*decl),
             FileLine::Draw(draw) => self.draw(draw),
             FileLine::Decor(decor) => self.decor(decor),
+            FileLine::Def(def) => self.define(*def),
+            FileLine::Include(file) => self.include(file),
+            FileLine::For(def) => self.for_loop(*def),
+            FileLine::Assert(predicate, args, written) => self.assert(predicate, args, written),
         }
     }
+    /// Interpret another file, relative to the current one.
+    ///
+    /// Errors in that file are reported with their location in it.
+    #[inline]
+    fn include(&mut self, file: String) -> Result<()> {
+        let path = match self.files.last().and_then(|current| current.parent()) {
+            Some(dir) => dir.join(&file),
+            None => PathBuf::from(&file),
+        };
+        if let Ok(canonical) = fs::canonicalize(&path) {
+            if self
+                .files
+                .iter()
+                .any(|f| fs::canonicalize(f).is_ok_and(|f| f == canonical))
+            {
+                return Err(InterpretError::CircularInclude(file))?;
+            }
+        }
+        let source = fs::read_to_string(&path).map_err(|e| InterpretError::ReadError(file, e))?;
+        self.files.push(path.clone());
+        let result = match parse(&source) {
+            Err(e) => Err(Diagnostic::from_parse_error(&e, &source, Some(&path)))?,
+            Ok(input) => self.interpret_main(input, &source, Some(&path)),
+        };
+        self.files.pop();
+        result
+    }
     #[inline]
     fn config(&mut self, config: Config) -> Result<()> {
         for (key, value) in config {
@@ -147,195 +311,312 @@ impl InterpreterState {
         }
         Ok(())
     }
+    /// Interpret a declaration.
+    ///
+    /// Re-binding a name declared without depending on other objects to
+    /// another such declaration of the same kind updates everything constructed
+    /// from it. Otherwise the new declaration shadows the old one.
     #[inline]
     fn decl(&mut self, decl: Decl) -> Result<()> {
-        let Decl(left, right) = decl;
-        let value = self.decl_right(right)?;
-        match left {
-            DeclLeft::Direct(x) => {
-                self.objects.insert(x, value.0);
-            }
-            DeclLeft::Destruct(x, y) => {
-                self.objects.insert(x, value.0);
-                if y != "_" {
-                    self.objects.insert(y, value.1);
-                }
+        let Decl(left, right, span) = decl;
+        let reads = self.reads(&right);
+        let result = self
+            .decl_right(right.clone())
+            .and_then(|value| Ok((bind(&left, &value)?, value)))
+            .at(span);
+        let (bound, value) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                self.poison(&left);
+                return Err(e);
             }
+        };
+        if_chain! {
+            if reads.is_empty();
+            if let DeclLeft::Direct(x) = &left;
+            if let Some(id) = self.free_binding(x, &value);
+            then { return self.rebind(id, right, value).at(span); }
         }
+        self.objects.extend(bound);
+        self.record(DeclNode::new(left, right, reads, value));
         Ok(())
     }
-    /// Get the value on the right for a `decl` statement.
-    /// This method should always return a result of a tuple, so destrct assignment could work.
+    /// Get the values on the right for a `decl` statement.
+    /// This method returns every value, so destruct assignment could work.
     #[inline]
-    fn decl_right(&self, decl: DeclRight) -> Result<(GObject, GObject)> {
+    pub(super) fn decl_right(&mut self, decl: DeclRight) -> Result<Vec<GObject>> {
         match decl {
             DeclRight::OrthoCoord(x, y) => {
                 let x = self.get_numeric(*x)?;
                 let y = self.get_numeric(*y)?;
-                Ok((GObject::Point(Point { x, y }), GObject::None))
+                Ok(vec![GObject::Point(Point { x, y })])
             }
             DeclRight::PolarCoord(r, t) => {
                 let r = self.get_numeric(*r)?;
                 let t = self.get_numeric(*t)?;
-                Ok((
-                    GObject::Point(Point {
-                        x: r * t.cos(),
-                        y: r * t.sin(),
-                    }),
-                    GObject::None,
-                ))
+                Ok(vec![GObject::Point(Point {
+                    x: r * t.cos(),
+                    y: r * t.sin(),
+                })])
             }
-            DeclRight::Object(obj) => Ok((self.get_arg_obj(*obj)?, GObject::None)),
+            DeclRight::Object(obj) => Ok(vec![self.get_arg_obj(*obj)?]),
             DeclRight::Expr(method, args) => {
                 let mut gobjs = Vec::with_capacity(args.len());
-                // Get all arguments.
-                for arg in args {
-                    let obj = self.get_arg_obj(arg)?;
-                    gobjs.push(obj);
+                let mut keywords = Keywords::new();
+                let mut selectors = Vec::new();
+                // Get all arguments. Selectors only apply to intersections, and
+                // other keyword arguments are passed to the method.
+                for Spanned { inner, span } in args {
+                    if let Object::Keyword(key, value) = inner {
+                        if method == "i" {
+                            if let Some(selector) = self.selector(&key, &value).at(span)? {
+                                selectors.push(selector);
+                                continue;
+                            }
+                        }
+                        if let Object::Axis(_) = *value {
+                            return Err(FuncError::NoKeyword(method, key).into()).at(span);
+                        }
+                        keywords.insert(key, self.get_arg_obj(*value).at(span)?);
+                    } else {
+                        gobjs.push(self.get_arg_obj(inner).at(span)?);
+                    }
                 }
-                let func = FUNCTIONS.get(&method);
-                if let Some(func) = func {
-                    let result = func(gobjs)?;
-                    Ok(result)
+                let result = if let Some(def) = self.get_macro(&method) {
+                    if let Some(key) = keywords.into_keys().next() {
+                        Err(FuncError::NoKeyword(method, key))?
+                    }
+                    self.call_macro(&def, gobjs)?
+                } else if let Some(func) = FUNCTIONS.get(&method) {
+                    func(gobjs, keywords)?
                 } else {
                     Err(FuncError::NoFunc(method))?
-                }
+                };
+                // Selectors apply in order.
+                let result = selectors
+                    .into_iter()
+                    .try_fold(result, |values, selector| selector.select(values))?;
+                Ok(result)
             }
         }
     }
+    /// Check that a predicate holds. `written` is the assertion as written,
+    /// to report it.
+    #[inline]
+    fn assert(
+        &mut self,
+        predicate: String,
+        args: Vec<Spanned<Object>>,
+        written: String,
+    ) -> Result<()> {
+        let mut gobjs = Vec::with_capacity(args.len());
+        for Spanned { inner, span } in args {
+            gobjs.push(self.get_arg_obj(inner).at(span)?);
+        }
+        let func = PREDICATES.get(&predicate);
+        if let Some(func) = func {
+            let residual = func(gobjs)?;
+            if residual.holds() {
+                Ok(())
+            } else {
+                Err(InterpretError::AssertionFailed(written, residual.value))?
+            }
+        } else {
+            Err(InterpretError::NoPredicate(predicate))?
+        }
+    }
     #[inline]
     fn draw(&mut self, draw: Draw) -> Result<()> {
+        let mut errors = Vec::new();
         for step in draw {
-            let obj = StyledDObject {
-                obj: self.get_draw_obj(step.obj)?,
-                local_conf: step.config,
-                global_conf: &self.config,
-            };
-            // Emit code at the correct layer.
-            let layer = match obj.obj {
-                DObject::Point(_) => LayerType::Dots,
-                DObject::Segment(_) => LayerType::Lines,
-                DObject::Arc(_) => LayerType::Lines,
-                DObject::Angle3P(_, _, _) => LayerType::Decor,
-                DObject::Circle(_) => LayerType::Lines,
-                DObject::Polygon(_) => LayerType::Area,
-            };
-            self.layer.emit(layer, obj.to_string().as_str());
-            // If a label is present, emit that label.
-            if obj.get("label").is_some() {
-                self.layer.emit(LayerType::Text, obj.label()?.as_str());
+            if let Err(e) = self.draw_step(step) {
+                if !self.recover {
+                    return Err(e);
+                }
+                errors.push(e);
             }
         }
-        Ok(())
+        Errors::check(errors)
+    }
+    #[inline]
+    fn draw_step(&mut self, step: StyledObject) -> Result<()> {
+        self.record_drawing(Step::Draw(step))
     }
     #[inline]
     fn decor(&mut self, decor: Decor) -> Result<()> {
+        let mut errors = Vec::new();
         for step in decor {
-            let obj = StyledDObject {
-                obj: self.get_draw_obj(step.obj)?,
-                local_conf: step.config,
-                global_conf: &self.config,
-            };
-            self.layer
-                .emit(LayerType::Decor, obj.decor(&step.decor)?.as_str());
+            if let Err(e) = self.decor_step(step) {
+                if !self.recover {
+                    return Err(e);
+                }
+                errors.push(e);
+            }
         }
+        Errors::check(errors)
+    }
+    #[inline]
+    fn decor_step(&mut self, step: DecorObject) -> Result<()> {
+        self.record_drawing(Step::Decor(step))
+    }
+    /// Draw a step, and keep it so that it is drawn again when the objects it
+    /// reads are re-bound.
+    #[inline]
+    fn record_drawing(&mut self, step: Step) -> Result<()> {
+        let output = self.render(&step)?;
+        let reads = self.reads(step.obj());
+        self.record_step(Drawing {
+            step,
+            config: self.config.clone(),
+            reads,
+            output,
+        });
         Ok(())
     }
+    /// Get the SVG code of a step, with its layer.
+    pub(super) fn render(&mut self, step: &Step) -> Result<Output> {
+        let step = match step {
+            Step::Draw(step) => step,
+            Step::Decor(step) => {
+                let obj = StyledDObject {
+                    obj: self.get_draw_obj(step.obj.clone()).at(step.span)?,
+                    local_conf: step.config.clone(),
+                    global_conf: &self.config,
+                };
+                return Ok(vec![(
+                    LayerType::Decor,
+                    obj.decor(&step.decor).at(step.span)?,
+                )]);
+            }
+        };
+        let mut obj = StyledDObject {
+            obj: self.get_draw_obj(step.obj.clone()).at(step.span)?,
+            local_conf: step.config.clone(),
+            global_conf: &self.config,
+        };
+        if let DObject::Segment(seg) = &obj.obj {
+            let start = extension(obj.get("ext-start")).at(step.span)?;
+            let end = extension(obj.get("ext-end")).at(step.span)?;
+            if start != 0.0 || end != 0.0 {
+                let (min, max) = self.view()?;
+                obj.obj = DObject::Segment(seg.extend(start, end, min, max));
+            }
+        }
+        // Emit code at the correct layer.
+        let layer = match obj.obj {
+            DObject::Point(_) => LayerType::Dots,
+            DObject::Segment(_) => LayerType::Lines,
+            DObject::Arc(_) => LayerType::Lines,
+            DObject::Angle3P(_, _, _) => LayerType::Decor,
+            DObject::Circle(_) => LayerType::Lines,
+            DObject::Path(_) => LayerType::Lines,
+            DObject::Polygon(_) => LayerType::Area,
+        };
+        let mut output = vec![(layer, obj.to_string())];
+        // If a label is present, emit that label.
+        if obj.get("label").is_some() {
+            output.push((LayerType::Text, obj.label().at(step.span)?));
+        }
+        Ok(output)
+    }
     /// Emit the complete SVG code.
     #[inline]
     pub fn emit(&self) -> Result<String> {
-        let width = self.config.get("width").unwrap().try_into_f64()? * CM;
-        let height = self.config.get("height").unwrap().try_into_f64()? * CM;
+        let mut layer = Layer(HashMap::new());
+        for drawing in &self.drawings {
+            for (layer_type, string) in &drawing.output {
+                layer.emit(*layer_type, string);
+            }
+        }
+        let (min, max) = self.view()?;
+        let (width, height) = ((max.x - min.x) * CM, (max.y - min.y) * CM);
         Ok(format!(
             "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"{} {} {} {}\">\n{}\n{}\n{}\n{}\n{}\n</svg>\n",
             width,
             height,
-            if let Some(ConfigValue::Number(min_x)) = self.config.get("min-x") {
-                *min_x * CM
-            } else {
-                -width / 2.0
-            },
-            if let Some(ConfigValue::Number(min_y)) = self.config.get("min-y") {
-                -*min_y * CM
-            } else {
-                -height / 2.0
-            },
+            min.x * CM,
+            -max.y * CM,
             width,
             height,
-            self.layer.get(LayerType::Area),
-            self.layer.get(LayerType::Lines),
-            self.layer.get(LayerType::Decor),
-            self.layer.get(LayerType::Dots),
-            self.layer.get(LayerType::Text),
+            layer.get(LayerType::Area),
+            layer.get(LayerType::Lines),
+            layer.get(LayerType::Decor),
+            layer.get(LayerType::Dots),
+            layer.get(LayerType::Text),
+        ))
+    }
+    /// The bottom-left and top-right corners of the picture, in cm.
+    #[inline]
+    fn view(&self) -> Result<(Point, Point)> {
+        let width = self.config.get("width").unwrap().try_into_f64()?;
+        let height = self.config.get("height").unwrap().try_into_f64()?;
+        let min_x = if let Some(ConfigValue::Number(min_x)) = self.config.get("min-x") {
+            *min_x
+        } else {
+            -width / 2.0
+        };
+        // `min-y` is the `minY` of the `viewBox`, where the y-axis is flipped.
+        let max_y = if let Some(ConfigValue::Number(min_y)) = self.config.get("min-y") {
+            *min_y
+        } else {
+            height / 2.0
+        };
+        Ok((
+            Point {
+                x: min_x,
+                y: max_y - height,
+            },
+            Point {
+                x: min_x + width,
+                y: max_y,
+            },
         ))
     }
 
     // Auxiliary functions.
+    /// The error for a name without value.
+    #[inline]
+    pub(super) fn missing(&self, key: String) -> InterpretError {
+        if self.poisoned.contains(&key) {
+            InterpretError::Poisoned(key)
+        } else {
+            InterpretError::MissingKey(key)
+        }
+    }
     /// Get Linear objects.
     #[inline]
     fn get_linear(&self, lin: Linear) -> Result<Line> {
         match lin {
-            Linear::Name(s) => {
-                // let GObject::Line(l) = get!(self.objects, s);
-                // Ok(*l)
-                if let GObject::Line(l) = get!(self.objects, s) {
-                    Ok(*l)
-                } else {
-                    Err(InterpretError::WrongGeometricType)?
-                }
-            }
-            Linear::Line2P(a, b) => {
-                if_chain! {
-                    if let GObject::Point(a) = get!(self.objects, a);
-                    if let GObject::Point(b) = get!(self.objects, b);
-                    then { Ok(Line::from_2p(*a, *b)?) }
-                    else { Err(InterpretError::WrongGeometricType)? }
-                }
-            }
+            Linear::Name(s) => Ok(get!(self, s, Line)),
+            Linear::Line2P(a, b) => Ok(Line::from_2p(get!(self, a, Point), get!(self, b, Point))?),
         }
     }
     /// Get Numeric values.
     #[inline]
-    fn get_numeric(&self, num: Numeric) -> Result<f64> {
+    pub(super) fn get_numeric(&self, num: Numeric) -> Result<f64> {
         match num {
             Numeric::Number(x) => Ok(x),
-            Numeric::Name(s) => {
-                if let GObject::Number(x) = get!(self.objects, s) {
-                    Ok(*x)
-                } else {
-                    Err(InterpretError::WrongGeometricType)?
-                }
-            }
-            Numeric::Distance2P(a, b) => {
-                if_chain! {
-                    if let GObject::Point(a) = get!(self.objects, a);
-                    if let GObject::Point(b) = get!(self.objects, b);
-                    then { Ok(a.distance(*b)) }
-                    else { Err(InterpretError::WrongGeometricType)? }
-                }
-            }
+            Numeric::Name(s) => Ok(get!(self, s, Number)),
+            Numeric::Eval(expr) => match self.eval(&expr)? {
+                GObject::Number(x) => Ok(x),
+                obj => Err(InterpretError::WrongGeometricType("Number", obj.kind()))?,
+            },
+            Numeric::Distance2P(a, b) => Ok(get!(self, a, Point).distance(get!(self, b, Point))),
             Numeric::DistancePL(a, l) => {
-                if let GObject::Point(a) = get!(self.objects, a) {
-                    let l = self.get_linear(l)?;
-                    Ok(a.distance(l))
-                } else {
-                    Err(InterpretError::WrongGeometricType)?
-                }
+                let a = get!(self, a, Point);
+                let l = self.get_linear(l)?;
+                Ok(a.distance(l))
             }
             Numeric::Distance2L(k, l) => {
                 let k = self.get_linear(k)?;
                 let l = self.get_linear(l)?;
                 Ok(k.distance(l))
             }
-            Numeric::Angle3P(a, o, b) => {
-                if_chain! {
-                    if let GObject::Point(a) = get!(self.objects, a);
-                    if let GObject::Point(o) = get!(self.objects, o);
-                    if let GObject::Point(b) = get!(self.objects, b);
-                    then { Ok(angle(*a, *o, *b)?) }
-                    else { Err(InterpretError::WrongGeometricType)? }
-                }
-            }
+            Numeric::Angle3P(a, o, b) => Ok(angle(
+                get!(self, a, Point),
+                get!(self, o, Point),
+                get!(self, b, Point),
+            )?),
             Numeric::Angle2L(k, l) => {
                 let k = self.get_linear(k)?;
                 let l = self.get_linear(l)?;
@@ -345,129 +626,145 @@ impl InterpreterState {
     }
     /// Get common patterns in arguments and `draw_obj`s.
     #[inline]
-    fn get_common(&self, obj: Object) -> Result<GObject> {
+    fn get_common(&mut self, obj: Object) -> Result<GObject> {
         match obj {
-            Object::Name(s) => Ok(*get!(self.objects, s)),
-            Object::Circ3P(a, b, c) => {
-                if_chain! {
-                    if let GObject::Point(a) = get!(self.objects, a);
-                    if let GObject::Point(b) = get!(self.objects, b);
-                    if let GObject::Point(c) = get!(self.objects, c);
-                    then { Ok(GObject::Circle(Circle::from_3p(*a, *b, *c)?)) }
-                    else { Err(InterpretError::WrongGeometricType)? }
-                }
+            Object::Name(s) => Ok(*get!(self, s)),
+            Object::Ray(a, b) => {
+                let a = get!(self, a, Point);
+                let b = get!(self, b, Point);
+                // Rejects rays with the same start and end.
+                Line::from_2p(a, b)?;
+                Ok(GObject::Ray((a, b)))
             }
+            Object::Circ3P(a, b, c) => Ok(GObject::Circle(Circle::from_3p(
+                get!(self, a, Point),
+                get!(self, b, Point),
+                get!(self, c, Point),
+            )?)),
             Object::CircOr(o, r) => {
-                if let GObject::Point(o) = get!(self.objects, o) {
-                    let r = self.get_numeric(*r)?;
-                    Ok(GObject::Circle(Circle::from_center_radius(*o, r)?))
-                } else {
-                    Err(InterpretError::WrongGeometricType)?
-                }
+                let o = self.get_point(o)?;
+                let r = self.get_numeric(*r)?;
+                Ok(GObject::Circle(Circle::from_center_radius(o, r)?))
             }
             Object::CircOA(a, b) => {
-                if_chain! {
-                    if let GObject::Point(a) = get!(self.objects, a);
-                    if let GObject::Point(b) = get!(self.objects, b);
-                    then { Ok(GObject::Circle(Circle::from_center_point(*a, *b)?)) }
-                    else { Err(InterpretError::WrongGeometricType)? }
-                }
+                let a = self.get_point(a)?;
+                let b = self.get_point(b)?;
+                Ok(GObject::Circle(Circle::from_center_point(a, b)?))
             }
             Object::CircDiam(a, b) => {
-                if_chain! {
-                    if let GObject::Point(a) = get!(self.objects, a);
-                    if let GObject::Point(b) = get!(self.objects, b);
-                    then { Ok(GObject::Circle(Circle::from_center_point(
-                        midpoint(*a, *b),
-                        *b
-                    )?)) }
-                    else { Err(InterpretError::WrongGeometricType)? }
-                }
+                let a = get!(self, a, Point);
+                let b = get!(self, b, Point);
+                Ok(GObject::Circle(Circle::from_center_point(
+                    midpoint(a, b),
+                    b,
+                )?))
             }
             // This is assured by the parser.
             _ => unreachable!(),
         }
     }
+    /// Get a point given by its name or by a method call.
+    #[inline]
+    fn get_point(&mut self, point: PointExpr) -> Result<Point> {
+        match point {
+            PointExpr::Name(a) => Ok(get!(self, a, Point)),
+            PointExpr::Call(obj) => match self.get_arg_obj(*obj)? {
+                GObject::Point(p) => Ok(p),
+                obj => Err(InterpretError::WrongGeometricType("Point", obj.kind()))?,
+            },
+        }
+    }
     /// Get objects that appears in arguments.
     #[inline]
-    fn get_arg_obj(&self, obj: Object) -> Result<GObject> {
+    pub(super) fn get_arg_obj(&mut self, obj: Object) -> Result<GObject> {
         match obj {
-            Object::Line2P(a, b) => {
-                if_chain! {
-                    if let GObject::Point(a) = get!(self.objects, a);
-                    if let GObject::Point(b) = get!(self.objects, b);
-                    then { Ok(GObject::Line(Line::from_2p(*a, *b)?)) }
-                    else { Err(InterpretError::WrongGeometricType)? }
-                }
-            }
-            Object::Triangle(a, b, c) => {
-                if_chain! {
-                    if let GObject::Point(a) = get!(self.objects, a);
-                    if let GObject::Point(b) = get!(self.objects, b);
-                    if let GObject::Point(c) = get!(self.objects, c);
-                    then { Ok(GObject::Trig((*a, *b, *c))) }
-                    else { Err(InterpretError::WrongGeometricType)? }
-                }
-            }
+            Object::Line2P(a, b) => Ok(GObject::Line(Line::from_2p(
+                get!(self, a, Point),
+                get!(self, b, Point),
+            )?)),
+            Object::Triangle(a, b, c) => Ok(GObject::Trig((
+                get!(self, a, Point),
+                get!(self, b, Point),
+                get!(self, c, Point),
+            ))),
             Object::Numeric(n) => Ok(GObject::Number(self.get_numeric(*n)?)),
-            Object::Eval(expr) => {
-                let result = self.eval(&expr)?;
-                Ok(GObject::Number(result))
+            Object::Eval(expr) => self.eval(&expr),
+            Object::Keyword(key, _) => Err(InterpretError::UnexpectedKeyword(key))?,
+            Object::Call(right, n) => {
+                let values = self.decl_right(*right)?;
+                match values.get(n - 1) {
+                    Some(value) => Ok(*value),
+                    None => Err(InterpretError::PickError(n, values.len()))?,
+                }
             }
             _ => self.get_common(obj),
         }
     }
     /// Get objects to draw.
     #[inline]
-    fn get_draw_obj(&self, obj: Object) -> Result<DObject> {
+    fn get_draw_obj(&mut self, obj: Object) -> Result<DObject> {
         match obj {
-            Object::Line2P(a, b) => {
-                if_chain! {
-                    if let GObject::Point(a) = get!(self.objects, a);
-                    if let GObject::Point(b) = get!(self.objects, b);
-                    then { Ok(DObject::Segment(Segment { from: *a, to: *b })) }
-                    else { Err(InterpretError::WrongGeometricType)? }
-                }
-            }
-            Object::Arc(a, b, c) => {
-                if_chain! {
-                    if let GObject::Point(a) = get!(self.objects, a);
-                    if let GObject::Point(b) = get!(self.objects, b);
-                    if let GObject::Point(c) = get!(self.objects, c);
-                    then { Ok(DObject::Arc(Arc::from_3p(*a, *b, *c)?)) }
-                    else { Err(InterpretError::WrongGeometricType)? }
-                }
-            }
-            Object::ArcO(a, o, b) => {
-                if_chain! {
-                    if let GObject::Point(a) = get!(self.objects, a);
-                    if let GObject::Point(o) = get!(self.objects, o);
-                    if let GObject::Point(b) = get!(self.objects, b);
-                    then { Ok(DObject::Arc(Arc::from_center(*a, *o, *b)?)) }
-                    else { Err(InterpretError::WrongGeometricType)? }
-                }
-            }
+            Object::Line2P(a, b) => Ok(DObject::Segment(Segment {
+                from: get!(self, a, Point),
+                to: get!(self, b, Point),
+            })),
+            Object::Arc(a, b, c) => Ok(DObject::Arc(Arc::from_3p(
+                get!(self, a, Point),
+                get!(self, b, Point),
+                get!(self, c, Point),
+            )?)),
+            Object::ArcO(a, o, b) => Ok(DObject::Arc(Arc::from_center(
+                get!(self, a, Point),
+                get!(self, o, Point),
+                get!(self, b, Point),
+            )?)),
             Object::Polygon(p) => {
                 let mut points = Vec::with_capacity(p.len());
                 for s in p {
-                    if let GObject::Point(a) = get!(self.objects, s) {
-                        points.push(*a);
-                    } else {
-                        return Err(InterpretError::WrongGeometricType)?;
-                    }
+                    points.push(get!(self, s, Point));
                 }
                 Ok(DObject::Polygon(points))
             }
-            Object::Angle3P(a, b, c) => {
-                if_chain! {
-                    if let GObject::Point(a) = get!(self.objects, a);
-                    if let GObject::Point(b) = get!(self.objects, b);
-                    if let GObject::Point(c) = get!(self.objects, c);
-                    then { Ok(DObject::Angle3P(*a, *b, *c)) }
-                    else { Err(InterpretError::WrongGeometricType)? }
+            Object::Angle3P(a, b, c) => Ok(DObject::Angle3P(
+                get!(self, a, Point),
+                get!(self, b, Point),
+                get!(self, c, Point),
+            )),
+            _ => match self.get_arg_obj(obj)? {
+                GObject::Line(l) => {
+                    let (min, max) = self.view()?;
+                    match Segment::clip(l, min, max) {
+                        Some(seg) => Ok(DObject::Segment(seg)),
+                        None => Err(InterpretError::OutOfView)?,
+                    }
                 }
-            }
-            _ => self.get_common(obj)?.into(),
+                GObject::Conic(c) => {
+                    let (min, max) = self.view()?;
+                    Ok(DObject::Path(c.path(min, max)?))
+                }
+                GObject::Ray((a, b)) => {
+                    let (min, max) = self.view()?;
+                    Ok(DObject::Segment(Segment::new(a, b).extend(
+                        0.0,
+                        f64::INFINITY,
+                        min,
+                        max,
+                    )))
+                }
+                obj => obj.into(),
+            },
         }
     }
 }
+
+/// How far a segment is extended by `ext-start` or `ext-end`: `true` extends
+/// it to the edge of the picture, and a number by that length.
+#[inline]
+fn extension(value: Option<&ConfigValue>) -> Result<f64> {
+    match value {
+        None | Some(ConfigValue::Bool(false)) => Ok(0.0),
+        Some(ConfigValue::Bool(true)) => Ok(f64::INFINITY),
+        Some(ConfigValue::Number(x)) => Ok(*x),
+        Some(ConfigValue::String(_)) => Err(InterpretError::WrongConfigType)?,
+    }
+}
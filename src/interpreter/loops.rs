@@ -0,0 +1,288 @@
+// `for` loops, and indexed names like `P{k}` in their bodies.
+
+use super::{
+    diagnostic::{Diagnostic, Diagnostics, Errors, SpannedError},
+    graph::DeclNode,
+    interpret::InterpreterState,
+    parser::ast::*,
+    utils::GObject,
+};
+use anyhow::Result;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum LoopError {
+    #[error("Expected an integer, found {0}")]
+    NotInteger(f64),
+    #[error("Expected an integer, found {0}")]
+    WrongType(&'static str),
+    #[error("When {0} = {1}: {2}")]
+    Iteration(String, i64, anyhow::Error),
+}
+
+/// Replace the indices in names, like `{k + 1}` in `P{k + 1}`, by their values.
+pub(super) trait Substitute {
+    fn substitute(&mut self, state: &InterpreterState) -> Result<()>;
+}
+
+impl Substitute for String {
+    fn substitute(&mut self, state: &InterpreterState) -> Result<()> {
+        let mut from = 0;
+        while let Some(start) = self[from..].find('{').map(|i| from + i) {
+            let Some(len) = self[start..].find('}') else {
+                break;
+            };
+            // Only braces right after a name are indices. Other braces in
+            // expressions are left, and reported when evaluating.
+            if !self[..start].ends_with(|c: char| c.is_ascii_alphanumeric() || c == '_') {
+                from = start + 1;
+                continue;
+            }
+            let index = state.integer(&Numeric::Eval(self[start + 1..start + len].to_string()))?;
+            self.replace_range(start..=start + len, &index.to_string());
+            from = start;
+        }
+        Ok(())
+    }
+}
+
+impl<T: Substitute> Substitute for Box<T> {
+    #[inline]
+    fn substitute(&mut self, state: &InterpreterState) -> Result<()> {
+        self.as_mut().substitute(state)
+    }
+}
+
+impl Substitute for Linear {
+    fn substitute(&mut self, state: &InterpreterState) -> Result<()> {
+        match self {
+            Linear::Line2P(a, b) => {
+                a.substitute(state)?;
+                b.substitute(state)
+            }
+            Linear::Name(a) => a.substitute(state),
+        }
+    }
+}
+
+impl Substitute for Numeric {
+    fn substitute(&mut self, state: &InterpreterState) -> Result<()> {
+        match self {
+            Numeric::Distance2P(a, b) => {
+                a.substitute(state)?;
+                b.substitute(state)
+            }
+            Numeric::DistancePL(a, l) => {
+                a.substitute(state)?;
+                l.substitute(state)
+            }
+            Numeric::Distance2L(k, l) | Numeric::Angle2L(k, l) => {
+                k.substitute(state)?;
+                l.substitute(state)
+            }
+            Numeric::Angle3P(a, b, c) => {
+                a.substitute(state)?;
+                b.substitute(state)?;
+                c.substitute(state)
+            }
+            Numeric::Number(_) => Ok(()),
+            Numeric::Name(a) | Numeric::Eval(a) => a.substitute(state),
+        }
+    }
+}
+
+impl Substitute for PointExpr {
+    fn substitute(&mut self, state: &InterpreterState) -> Result<()> {
+        match self {
+            PointExpr::Name(a) => a.substitute(state),
+            PointExpr::Call(obj) => obj.substitute(state),
+        }
+    }
+}
+
+impl Substitute for Object {
+    fn substitute(&mut self, state: &InterpreterState) -> Result<()> {
+        match self {
+            Object::Line2P(a, b) | Object::Ray(a, b) | Object::CircDiam(a, b) => {
+                a.substitute(state)?;
+                b.substitute(state)
+            }
+            Object::Circ3P(a, b, c)
+            | Object::Arc(a, b, c)
+            | Object::ArcO(a, b, c)
+            | Object::Angle3P(a, b, c)
+            | Object::Triangle(a, b, c) => {
+                a.substitute(state)?;
+                b.substitute(state)?;
+                c.substitute(state)
+            }
+            Object::CircOr(a, r) => {
+                a.substitute(state)?;
+                r.substitute(state)
+            }
+            Object::CircOA(a, b) => {
+                a.substitute(state)?;
+                b.substitute(state)
+            }
+            Object::Polygon(p) => p.iter_mut().try_for_each(|a| a.substitute(state)),
+            Object::Name(a) | Object::Eval(a) => a.substitute(state),
+            Object::Numeric(n) => n.substitute(state),
+            Object::Keyword(_, value) => value.substitute(state),
+            Object::Axis(_) => Ok(()),
+            Object::Call(right, _) => right.substitute(state),
+        }
+    }
+}
+
+impl Substitute for DeclRight {
+    fn substitute(&mut self, state: &InterpreterState) -> Result<()> {
+        match self {
+            DeclRight::OrthoCoord(x, y) | DeclRight::PolarCoord(x, y) => {
+                x.substitute(state)?;
+                y.substitute(state)
+            }
+            DeclRight::Expr(_, args) => args
+                .iter_mut()
+                .try_for_each(|arg| arg.inner.substitute(state)),
+            DeclRight::Object(obj) => obj.substitute(state),
+        }
+    }
+}
+
+impl Substitute for FileLine {
+    fn substitute(&mut self, state: &InterpreterState) -> Result<()> {
+        match self {
+            FileLine::Decl(decl) => {
+                let Decl(left, right, _) = decl.as_mut();
+                match left {
+                    DeclLeft::Direct(x) => x.substitute(state)?,
+                    DeclLeft::Destruct(names) => {
+                        names.iter_mut().try_for_each(|x| x.substitute(state))?
+                    }
+                }
+                right.substitute(state)
+            }
+            FileLine::Assert(_, args, _) => args
+                .iter_mut()
+                .try_for_each(|arg| arg.inner.substitute(state)),
+            FileLine::Draw(draw) => draw
+                .iter_mut()
+                .try_for_each(|step| step.obj.substitute(state)),
+            FileLine::Decor(decor) => decor
+                .iter_mut()
+                .try_for_each(|step| step.obj.substitute(state)),
+            // Names in a nested loop may depend on its own variable, so they
+            // are substituted when it runs.
+            FileLine::For(_) | FileLine::Config(_) | FileLine::Def(_) | FileLine::Include(_) => {
+                Ok(())
+            }
+        }
+    }
+}
+
+impl InterpreterState {
+    /// Evaluate a numeric value that should be an integer.
+    fn integer(&self, num: &Numeric) -> Result<i64> {
+        let x = match num {
+            Numeric::Eval(expr) => match self.eval(expr)? {
+                GObject::Number(x) => x,
+                obj => Err(LoopError::WrongType(obj.kind()))?,
+            },
+            num => self.get_numeric(num.clone())?,
+        };
+        if x.fract() == 0.0 {
+            Ok(x as i64)
+        } else {
+            Err(LoopError::NotInteger(x))?
+        }
+    }
+    /// Interpret the body of a loop once for each value of its variable.
+    ///
+    /// The variable is only bound inside the loop, but objects declared in
+    /// the body are kept.
+    pub(super) fn for_loop(&mut self, def: ForLoop) -> Result<()> {
+        let ForLoop {
+            var,
+            from,
+            to,
+            body,
+        } = def;
+        let (from, to) = (self.integer(&from)?, self.integer(&to)?);
+        let saved = (
+            self.objects.get(&var).copied(),
+            self.bindings.get(&var).copied(),
+        );
+        let mut errors = Vec::new();
+        let mut node: Option<usize> = None;
+        'outer: for k in from..to {
+            // Bound like a declaration, so that objects depending on it can be
+            // recomputed with the value of this iteration.
+            let value = GObject::Number(k as f64);
+            let right = DeclRight::Object(Box::new(Object::Numeric(Box::new(Numeric::Number(
+                k as f64,
+            )))));
+            self.objects.insert(var.clone(), value);
+            match node {
+                // Nothing read the value of the last iteration, so its node can
+                // be reused.
+                Some(id)
+                    if self.nodes[id].dependents.is_empty()
+                        && self.nodes[id].drawings.is_empty() =>
+                {
+                    self.nodes[id].right = right;
+                    self.nodes[id].value = vec![value];
+                    self.bindings.insert(var.clone(), id);
+                    self.poisoned.remove(&var);
+                }
+                _ => {
+                    node = Some(self.nodes.len());
+                    self.record(DeclNode::new(
+                        DeclLeft::Direct(var.clone()),
+                        right,
+                        Vec::new(),
+                        vec![value],
+                    ));
+                }
+            }
+            for Spanned { inner, span } in body.clone() {
+                if let Err(e) = self._interpret(inner) {
+                    let e = match e.downcast::<Errors>() {
+                        Ok(Errors(e)) => e,
+                        Err(e) => vec![e],
+                    };
+                    errors.extend(e.into_iter().map(|e| iteration(&var, k, span, e)));
+                    if !self.recover {
+                        break 'outer;
+                    }
+                }
+            }
+        }
+        match saved.0 {
+            Some(obj) => self.objects.insert(var.clone(), obj),
+            None => self.objects.remove(&var),
+        };
+        match saved.1 {
+            Some(id) => self.bindings.insert(var, id),
+            None => self.bindings.remove(&var),
+        };
+        Errors::check(errors)
+    }
+}
+
+/// Tell in which iteration an error happens. Errors in included files are
+/// kept as they are.
+#[inline]
+fn iteration(var: &str, k: i64, span: Span, error: anyhow::Error) -> anyhow::Error {
+    if error.is::<Diagnostic>() || error.is::<Diagnostics>() {
+        return error;
+    }
+    let (span, error) = match error.downcast::<SpannedError>() {
+        Ok(e) => (e.span, e.error),
+        Err(e) => (span, e),
+    };
+    SpannedError {
+        span,
+        error: LoopError::Iteration(var.to_string(), k, error).into(),
+    }
+    .into()
+}
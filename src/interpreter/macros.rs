@@ -0,0 +1,78 @@
+// User-defined construction procedures, declared with `def`.
+
+use super::{
+    diagnostic::SpannedError,
+    graph::bind,
+    interpret::InterpreterState,
+    parser::ast::{Decl, Macro},
+    utils::GObject,
+};
+use anyhow::Result;
+use std::{collections::HashMap, rc::Rc};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MacroError {
+    #[error("Macro {0} takes {1} argument(s), but {2} given")]
+    ArgCount(String, usize, usize),
+    #[error("Macro {0} calls itself")]
+    Recursive(String),
+    #[error("In macro {0}: {1}")]
+    Failed(String, anyhow::Error),
+}
+
+impl InterpreterState {
+    /// Define a macro, replacing any macro of the same name.
+    #[inline]
+    pub(super) fn define(&mut self, def: Macro) -> Result<()> {
+        self.macros.insert(def.name.clone(), Rc::new(def));
+        Ok(())
+    }
+    /// Get the macro called `name`, if any.
+    #[inline]
+    pub(super) fn get_macro(&self, name: &str) -> Option<Rc<Macro>> {
+        self.macros.get(name).cloned()
+    }
+    /// Call a macro. The body is evaluated in a scope only containing the
+    /// parameters, so it cannot read or change objects outside.
+    pub(super) fn call_macro(&mut self, def: &Macro, args: Vec<GObject>) -> Result<Vec<GObject>> {
+        if def.params.len() != args.len() {
+            Err(MacroError::ArgCount(
+                def.name.clone(),
+                def.params.len(),
+                args.len(),
+            ))?
+        }
+        if self.calling.contains(&def.name) {
+            Err(MacroError::Recursive(def.name.clone()))?
+        }
+        let scope: HashMap<String, GObject> = def.params.iter().cloned().zip(args).collect();
+        let objects = std::mem::replace(&mut self.objects, scope);
+        self.calling.push(def.name.clone());
+        let result = self.run_macro(def);
+        self.calling.pop();
+        let scope = std::mem::replace(&mut self.objects, objects);
+        if let Err(e) = result {
+            // The location in the body is not helpful at the call site.
+            Err(MacroError::Failed(def.name.clone(), SpannedError::strip(e)))?
+        }
+        Ok(def
+            .outputs
+            .iter()
+            .map(|name| scope.get(name).copied().unwrap_or(GObject::None))
+            .collect())
+    }
+    #[inline]
+    fn run_macro(&mut self, def: &Macro) -> Result<()> {
+        for Decl(left, right, _) in &def.body {
+            let value = self.decl_right(right.clone())?;
+            self.objects.extend(bind(left, &value)?);
+        }
+        for name in &def.outputs {
+            if !self.objects.contains_key(name) {
+                Err(self.missing(name.clone()))?
+            }
+        }
+        Ok(())
+    }
+}
@@ -13,6 +13,15 @@ type Node<'i> = pest_consume::Node<'i, Rule, ()>;
 #[grammar = "interpreter/parser.pest"]
 struct ProposeParser;
 
+#[inline]
+fn span_of(input: &Node) -> Span {
+    let span = input.as_span();
+    Span {
+        start: span.start(),
+        end: span.end(),
+    }
+}
+
 #[inline]
 #[allow(clippy::result_large_err)]
 pub fn parse(src: &str) -> Result<Main> {
@@ -39,6 +48,13 @@ impl ProposeParser {
         )
     }
     #[inline]
+    fn ray(input: Node) -> Result<Object> {
+        match_nodes!(
+            input.into_children();
+            [point_id(a), point_id(b)] => Ok(Object::Ray(a, b))
+        )
+    }
+    #[inline]
     fn circ_3p(input: Node) -> Result<Object> {
         match_nodes!(
             input.into_children();
@@ -49,14 +65,34 @@ impl ProposeParser {
     fn circ_or(input: Node) -> Result<Object> {
         match_nodes!(
             input.into_children();
-            [point_id(a), numeric(b)] => Ok(Object::CircOr(a, Box::new(b))),
+            [point_expr(a), num_or_eval(b)] => Ok(Object::CircOr(a, Box::new(b))),
         )
     }
     #[inline]
     fn circ_oa(input: Node) -> Result<Object> {
         match_nodes!(
             input.into_children();
-            [point_id(a), point_id(b)] => Ok(Object::CircOA(a, b))
+            [point_expr(a), point_expr(b)] => Ok(Object::CircOA(a, b))
+        )
+    }
+    #[inline]
+    fn short_call(input: Node) -> Result<Object> {
+        let span = span_of(&input);
+        match_nodes!(
+            input.into_children();
+            [method(a), arg(b)] => {
+                let arg = Spanned { inner: b, span };
+                Ok(Object::Call(Box::new(DeclRight::Expr(a, vec![arg])), 1))
+            }
+        )
+    }
+    #[inline]
+    fn point_expr(input: Node) -> Result<PointExpr> {
+        match_nodes!(
+            input.into_children();
+            [call(a)] => Ok(PointExpr::Call(Box::new(a))),
+            [short_call(a)] => Ok(PointExpr::Call(Box::new(a))),
+            [point_id(a)] => Ok(PointExpr::Name(a)),
         )
     }
     #[inline]
@@ -187,14 +223,25 @@ impl ProposeParser {
     fn ortho_coord(input: Node) -> Result<DeclRight> {
         match_nodes!(
             input.into_children();
-            [numeric(a), numeric(b)] => Ok(DeclRight::OrthoCoord(Box::new(a), Box::new(b)))
+            [num_or_eval(a), num_or_eval(b)] => Ok(DeclRight::OrthoCoord(Box::new(a), Box::new(b)))
         )
     }
     #[inline]
     fn polar_coord(input: Node) -> Result<DeclRight> {
         match_nodes!(
             input.into_children();
-            [numeric(a), numeric(b)] => Ok(DeclRight::PolarCoord(Box::new(a), Box::new(b)))
+            [num_or_eval(a), num_or_eval(b)] => Ok(DeclRight::PolarCoord(Box::new(a), Box::new(b)))
+        )
+    }
+    #[inline]
+    fn num_or_eval(input: Node) -> Result<Numeric> {
+        match_nodes!(
+            input.into_children();
+            [numeric(a)] => Ok(a),
+            [eval(a)] => match a {
+                Object::Eval(expr) => Ok(Numeric::Eval(expr)),
+                _ => unreachable!(),
+            },
         )
     }
     #[inline]
@@ -227,6 +274,7 @@ impl ProposeParser {
     fn common_obj(input: Node) -> Result<Object> {
         match_nodes!(
             input.into_children();
+            [ray(a)] => Ok(a),
             [line_2p(a)] => Ok(a),
             [circ_3p(a)] => Ok(a),
             [circ_or(a)] => Ok(a),
@@ -239,6 +287,7 @@ impl ProposeParser {
     fn arg(input: Node) -> Result<Object> {
         match_nodes!(
             input.into_children();
+            [call(a)] => Ok(a),
             [trig(a)] => Ok(a),
             [common_obj(a)] => Ok(a),
             [numeric(a)] => Ok(Object::Numeric(Box::new(a))),
@@ -249,6 +298,7 @@ impl ProposeParser {
     fn draw_obj(input: Node) -> Result<Object> {
         match_nodes!(
             input.into_children();
+            [call(a)] => Ok(a),
             [polygon(a)] => Ok(a),
             [arc(a)] => Ok(a),
             [arc_o(a)] => Ok(a),
@@ -272,8 +322,58 @@ impl ProposeParser {
         Ok(input.as_str().to_string())
     }
     #[inline]
-    fn args(input: Node) -> Result<Vec<Object>> {
-        input.into_children().map(Self::arg).collect()
+    fn def_name(input: Node) -> Result<String> {
+        Ok(input.as_str().to_string())
+    }
+    #[inline]
+    fn pick(input: Node) -> Result<usize> {
+        input.as_str()[1..]
+            .parse::<usize>()
+            .map_err(|e| input.error(e))
+    }
+    #[inline]
+    fn call(input: Node) -> Result<Object> {
+        match_nodes!(
+            input.into_children();
+            [expr(a)] => Ok(Object::Call(Box::new(a), 1)),
+            [expr(a), pick(n)] => Ok(Object::Call(Box::new(a), n)),
+        )
+    }
+    #[inline]
+    fn keyword(input: Node) -> Result<Object> {
+        match_nodes!(
+            input.into_children();
+            [config_name(a), arg(b)] => Ok(Object::Keyword(a, Box::new(b)))
+        )
+    }
+    #[inline]
+    fn axis(input: Node) -> Result<Axis> {
+        match input.as_str() {
+            "x" => Ok(Axis::X),
+            _ => Ok(Axis::Y),
+        }
+    }
+    #[inline]
+    fn order(input: Node) -> Result<Object> {
+        match_nodes!(
+            input.into_children();
+            [axis(a)] => Ok(Object::Keyword(String::from("order"), Box::new(Object::Axis(a))))
+        )
+    }
+    #[inline]
+    fn args(input: Node) -> Result<Vec<Spanned<Object>>> {
+        input
+            .into_children()
+            .map(|arg| {
+                let span = span_of(&arg);
+                let inner = match arg.as_rule() {
+                    Rule::keyword => Self::keyword(arg)?,
+                    Rule::order => Self::order(arg)?,
+                    _ => Self::arg(arg)?,
+                };
+                Ok(Spanned { inner, span })
+            })
+            .collect()
     }
     #[inline]
     fn expr(input: Node) -> Result<DeclRight> {
@@ -303,7 +403,7 @@ impl ProposeParser {
     fn destruct(input: Node) -> Result<DeclLeft> {
         match_nodes!(
             input.into_children();
-            [any_id(a), any_id(b)] => Ok(DeclLeft::Destruct(a, b))
+            [any_id(names)..] => Ok(DeclLeft::Destruct(names.collect()))
         )
     }
     #[inline]
@@ -316,9 +416,29 @@ impl ProposeParser {
     }
     #[inline]
     fn decl(input: Node) -> Result<Decl> {
+        let span = span_of(&input);
+        match_nodes!(
+            input.into_children();
+            [decl_left(a), decl_right(b)] => Ok(Decl(a, b, span))
+        )
+    }
+    #[inline]
+    fn params(input: Node) -> Result<Vec<String>> {
         match_nodes!(
             input.into_children();
-            [decl_left(a), decl_right(b)] => Ok(Decl(a, b))
+            [any_id(a)..] => Ok(a.collect())
+        )
+    }
+    #[inline]
+    fn def(input: Node) -> Result<Macro> {
+        match_nodes!(
+            input.into_children();
+            [def_name(name), params(params), params(outputs), decl(body)..] => Ok(Macro {
+                name,
+                params,
+                outputs,
+                body: body.collect(),
+            })
         )
     }
     #[inline]
@@ -351,15 +471,46 @@ impl ProposeParser {
         Ok(map)
     }
     #[inline]
+    fn include(input: Node) -> Result<String> {
+        match_nodes!(
+            input.into_children();
+            [string(a)] => Ok(a)
+        )
+    }
+    #[inline]
+    fn assert(input: Node) -> Result<(String, Vec<Spanned<Object>>, String)> {
+        let written = input.as_str()["assert".len()..]
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+        match_nodes!(
+            input.into_children();
+            [method(a), args(b)] => Ok((a, b, written))
+        )
+    }
+    #[inline]
+    fn for_loop(input: Node) -> Result<ForLoop> {
+        match_nodes!(
+            input.into_children();
+            [common_id(var), num_or_eval(from), num_or_eval(to), file_line(body)..] => Ok(ForLoop {
+                var,
+                from,
+                to,
+                body: body.collect(),
+            })
+        )
+    }
+    #[inline]
     fn config_line(input: Node) -> Result<Config> {
         Self::configs(input.into_children().single().unwrap())
     }
     #[inline]
     fn draw_step(input: Node) -> Result<StyledObject> {
+        let span = span_of(&input);
         match_nodes!(
             input.into_children();
-            [draw_obj(obj), configs(config)] => Ok(StyledObject { obj, config: Some(config) }),
-            [draw_obj(obj)] => Ok(StyledObject { obj, config: None })
+            [draw_obj(obj), configs(config)] => Ok(StyledObject { obj, config: Some(config), span }),
+            [draw_obj(obj)] => Ok(StyledObject { obj, config: None, span })
         )
     }
     #[inline]
@@ -372,10 +523,11 @@ impl ProposeParser {
     }
     #[inline]
     fn decor_step(input: Node) -> Result<DecorObject> {
+        let span = span_of(&input);
         match_nodes!(
             input.into_children();
-            [decor_obj(obj), decoration(decor), configs(config)] => Ok(DecorObject { obj, decor, config: Some(config) }),
-            [decor_obj(obj), decoration(decor)] => Ok(DecorObject { obj, decor, config: None })
+            [decor_obj(obj), decoration(decor), configs(config)] => Ok(DecorObject { obj, decor, config: Some(config), span }),
+            [decor_obj(obj), decoration(decor)] => Ok(DecorObject { obj, decor, config: None, span })
         )
     }
     #[inline]
@@ -383,14 +535,20 @@ impl ProposeParser {
         input.into_children().map(Self::decor_step).collect()
     }
     #[inline]
-    fn file_line(input: Node) -> Result<FileLine> {
-        match_nodes!(
-            input.into_children();
-            [config_line(a)] => Ok(FileLine::Config(a)),
-            [draw(a)] => Ok(FileLine::Draw(a)),
-            [decor(a)] => Ok(FileLine::Decor(a)),
-            [decl(a)] => Ok(FileLine::Decl(Box::new(a))),
-        )
+    fn file_line(input: Node) -> Result<Spanned<FileLine>> {
+        let span = span_of(&input);
+        let inner = match_nodes!(
+            input.into_children();
+            [config_line(a)] => FileLine::Config(a),
+            [draw(a)] => FileLine::Draw(a),
+            [decor(a)] => FileLine::Decor(a),
+            [decl(a)] => FileLine::Decl(Box::new(a)),
+            [def(a)] => FileLine::Def(Box::new(a)),
+            [include(a)] => FileLine::Include(a),
+            [for_loop(a)] => FileLine::For(Box::new(a)),
+            [assert((a, b, c))] => FileLine::Assert(a, b, c),
+        );
+        Ok(Spanned { inner, span })
     }
     #[inline]
     fn main(input: Node) -> Result<Main> {
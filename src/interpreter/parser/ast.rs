@@ -1,17 +1,41 @@
 use crate::interpreter::utils::ConfigValue;
 #[cfg(test)]
 use serde::Serialize;
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::Deref};
+
+/// Byte range of a node in the source, used to locate errors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A node together with its span in the source.
+#[cfg_attr(test, derive(Serialize), serde(transparent))]
+#[derive(Debug, Clone)]
+pub struct Spanned<T> {
+    pub inner: T,
+    #[cfg_attr(test, serde(skip))]
+    pub span: Span,
+}
+
+impl<T> Deref for Spanned<T> {
+    type Target = T;
+    #[inline]
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
 
 #[cfg_attr(test, derive(Serialize))]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Linear {
     Line2P(String, String),
     Name(String),
 }
 
 #[cfg_attr(test, derive(Serialize))]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum Numeric {
     Distance2P(String, String),
     DistancePL(String, Linear),
@@ -20,15 +44,33 @@ pub enum Numeric {
     Angle2L(Linear, Linear),
     Number(f64),
     Name(String),
+    Eval(String),
+}
+
+/// A point given by its name, or by a method call like `cO ABC`.
+#[cfg_attr(test, derive(Serialize), serde(untagged))]
+#[derive(Debug, Clone)]
+pub enum PointExpr {
+    Name(String),
+    Call(Box<Object>),
 }
 
+/// A coordinate, as in `order=x`.
 #[cfg_attr(test, derive(Serialize))]
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
+pub enum Axis {
+    X,
+    Y,
+}
+
+#[cfg_attr(test, derive(Serialize))]
+#[derive(Debug, Clone)]
 pub enum Object {
     Line2P(String, String),
+    Ray(String, String),
     Circ3P(String, String, String),
-    CircOr(String, Box<Numeric>),
-    CircOA(String, String),
+    CircOr(PointExpr, Box<Numeric>),
+    CircOA(PointExpr, PointExpr),
     CircDiam(String, String),
     Arc(String, String, String),
     ArcO(String, String, String),
@@ -38,55 +80,101 @@ pub enum Object {
     Name(String),
     Numeric(Box<Numeric>),
     Eval(String),
+    /// A keyword argument `name=value`.
+    Keyword(String, Box<Object>),
+    /// The value of `order=x` or `order=y`.
+    Axis(Axis),
+    /// A nested method call, and the position of the value it stands for,
+    /// counting from 1.
+    Call(Box<DeclRight>, usize),
 }
 
 #[cfg_attr(test, derive(Serialize))]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum DeclRight {
     OrthoCoord(Box<Numeric>, Box<Numeric>),
     PolarCoord(Box<Numeric>, Box<Numeric>),
-    Expr(String, Vec<Object>),
+    Expr(String, Vec<Spanned<Object>>),
     Object(Box<Object>),
 }
 
 #[cfg_attr(test, derive(Serialize))]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum DeclLeft {
     Direct(String),
-    Destruct(String, String),
+    Destruct(Vec<String>),
 }
 
 #[cfg_attr(test, derive(Serialize))]
-#[derive(Debug)]
-pub struct Decl(pub DeclLeft, pub DeclRight);
+#[derive(Debug, Clone)]
+pub struct Decl(
+    pub DeclLeft,
+    pub DeclRight,
+    #[cfg_attr(test, serde(skip))] pub Span,
+);
+
+/// A user-defined construction procedure, declared with `def`.
+#[cfg_attr(test, derive(Serialize))]
+#[derive(Debug, Clone)]
+pub struct Macro {
+    pub name: String,
+    pub params: Vec<String>,
+    pub outputs: Vec<String>,
+    pub body: Vec<Decl>,
+}
 
 pub type Config = HashMap<String, ConfigValue>;
 
 #[cfg_attr(test, derive(Serialize))]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct StyledObject {
     pub obj: Object,
     pub config: Option<Config>,
+    #[cfg_attr(test, serde(skip))]
+    pub span: Span,
 }
 
 #[cfg_attr(test, derive(Serialize))]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct DecorObject {
     pub obj: Object,
     pub decor: String,
     pub config: Option<Config>,
+    #[cfg_attr(test, serde(skip))]
+    pub span: Span,
 }
 
 pub type Draw = Vec<StyledObject>;
 pub type Decor = Vec<DecorObject>;
 
 #[cfg_attr(test, derive(Serialize))]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum FileLine {
     Config(Config),
     Draw(Draw),
     Decor(Decor),
     Decl(Box<Decl>),
+    Def(Box<Macro>),
+    /// Path of a file to interpret, relative to the current file.
+    Include(String),
+    For(Box<ForLoop>),
+    /// A predicate that should hold, its arguments, and the assertion as
+    /// written, to report it.
+    Assert(
+        String,
+        Vec<Spanned<Object>>,
+        #[cfg_attr(test, serde(skip))] String,
+    ),
+}
+
+/// A loop over the integers from `from` (inclusive) to `to` (exclusive).
+#[cfg_attr(test, derive(Serialize))]
+#[derive(Debug, Clone)]
+pub struct ForLoop {
+    pub var: String,
+    pub from: Numeric,
+    pub to: Numeric,
+    pub body: Vec<Spanned<FileLine>>,
 }
 
-pub type Main = Vec<FileLine>;
+pub type Main = Vec<Spanned<FileLine>>;
@@ -0,0 +1,117 @@
+// Selectors picking among the points returned by a method, given as keyword
+// arguments like `near=P` in `i l, c, near=P`.
+#![allow(non_snake_case)]
+
+use super::{
+    builtin::predicates::TOLERANCE,
+    interpret::InterpreterState,
+    parser::ast::{Axis, Object},
+    utils::GObject,
+};
+use anyhow::Result;
+use metric_rs::{
+    calc::basic::Distance,
+    objects::{Line, Point},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum SelectError {
+    #[error("Wrong value of {0}: expected {1}, found {2}")]
+    WrongValue(String, &'static str, &'static str),
+    #[error("Points can only be ordered by x or y")]
+    NoAxis,
+    #[error("Only points can be selected, found {0}")]
+    NotPoint(&'static str),
+    #[error("No point is on the left of the line")]
+    NoneLeft,
+}
+
+/// A way to pick among the points returned by a method.
+#[derive(Debug, Clone, Copy)]
+pub enum Selector {
+    /// The point nearest to a point.
+    Near(Point),
+    /// The point farthest from a point.
+    Far(Point),
+    /// The points on the left of a line, oriented like `Line::from_2p`.
+    LeftOf(Line),
+    /// Every point, in increasing order of a coordinate.
+    Order(Axis),
+}
+
+impl Selector {
+    /// Apply the selector to the values returned by a method, which should
+    /// all be points.
+    pub fn select(self, values: Vec<GObject>) -> Result<Vec<GObject>, SelectError> {
+        let mut points = values
+            .into_iter()
+            .map(|value| match value {
+                GObject::Point(p) => Ok(p),
+                value => Err(SelectError::NotPoint(value.kind())),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let by_distance =
+            |P: Point| move |p: &Point, q: &Point| p.distance(P).total_cmp(&q.distance(P));
+        match self {
+            Selector::Near(P) => {
+                points = points
+                    .into_iter()
+                    .min_by(by_distance(P))
+                    .into_iter()
+                    .collect()
+            }
+            Selector::Far(P) => {
+                points = points
+                    .into_iter()
+                    .max_by(by_distance(P))
+                    .into_iter()
+                    .collect()
+            }
+            Selector::LeftOf(l) => {
+                // `Line::from_2p(A, B)` is negative on the left of `AB`.
+                let norm = l.a.hypot(l.b);
+                points.retain(|p| (l.a * p.x + l.b * p.y + l.c) / norm < -TOLERANCE);
+                if points.is_empty() {
+                    return Err(SelectError::NoneLeft);
+                }
+            }
+            Selector::Order(Axis::X) => points.sort_by(|p, q| p.x.total_cmp(&q.x)),
+            Selector::Order(Axis::Y) => points.sort_by(|p, q| p.y.total_cmp(&q.y)),
+        }
+        Ok(points.into_iter().map(GObject::Point).collect())
+    }
+}
+
+impl InterpreterState {
+    /// Get the selector given by the keyword argument `key=value`, or `None`
+    /// if `key` is not a selector.
+    pub(super) fn selector(&mut self, key: &str, value: &Object) -> Result<Option<Selector>> {
+        let selector = match key {
+            "near" | "far" => match self.get_arg_obj(value.clone())? {
+                GObject::Point(p) if key == "near" => Selector::Near(p),
+                GObject::Point(p) => Selector::Far(p),
+                obj => Err(SelectError::WrongValue(
+                    key.to_string(),
+                    "Point",
+                    obj.kind(),
+                ))?,
+            },
+            "left-of" => match self.get_arg_obj(value.clone())? {
+                GObject::Line(l) => Selector::LeftOf(l),
+                GObject::Ray((a, b)) => Selector::LeftOf(Line::from_2p(a, b)?),
+                obj => Err(SelectError::WrongValue(
+                    key.to_string(),
+                    "Line or Ray",
+                    obj.kind(),
+                ))?,
+            },
+            "order" => match value {
+                Object::Axis(axis) => Selector::Order(*axis),
+                _ => Err(SelectError::NoAxis)?,
+            },
+            _ => return Ok(None),
+        };
+        Ok(Some(selector))
+    }
+}
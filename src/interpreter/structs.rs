@@ -2,6 +2,9 @@
 // `Segment` and `Arc`.
 #![allow(non_snake_case)]
 
+pub mod conic;
+pub mod transform;
+
 use std::f64::consts::PI;
 
 use metric_rs::{
@@ -11,7 +14,7 @@ use metric_rs::{
         point_on::PointOn,
         transform::Rotate,
     },
-    objects::{Circle, Point},
+    objects::{Circle, Line, Point},
 };
 
 #[derive(Debug, Clone)]
@@ -31,6 +34,13 @@ pub struct Arc {
     pub angle: f64,
 }
 
+/// A curve drawn through sampled points, possibly in several pieces.
+#[derive(Debug, Clone)]
+pub struct Path {
+    pub pieces: Vec<Vec<Point>>,
+    pub closed: bool,
+}
+
 impl Arc {
     pub fn from_3p(A: Point, B: Point, C: Point) -> CalcResult<Self> {
         let Circle { O, r } = Circle::from_3p(A, B, C)?;
@@ -79,6 +89,77 @@ impl Segment {
     pub fn new(A: Point, B: Point) -> Self {
         Segment { from: A, to: B }
     }
+    /// The part of a line inside the rectangle with corners `min` and `max`,
+    /// going from left to right (or upwards, if the line is vertical).
+    /// Returns `None` if the line does not cross the rectangle.
+    pub fn clip(line: Line, min: Point, max: Point) -> Option<Self> {
+        let Line { a, b, c } = line;
+        let norm = a * a + b * b;
+        // The point on the line closest to the origin, and the direction.
+        let P = Point {
+            x: -a * c / norm,
+            y: -b * c / norm,
+        };
+        let dir = if b > 0.0 || (b == 0.0 && a < 0.0) {
+            Point { x: b, y: -a }
+        } else {
+            Point { x: -b, y: a }
+        };
+        let (start, end) = clip_range(P, dir, min, max)?;
+        Some(Segment {
+            from: P + dir * start,
+            to: P + dir * end,
+        })
+    }
+    /// Extend the segment past its start and its end by the given lengths.
+    /// An infinite length extends it to the edge of the rectangle with
+    /// corners `min` and `max`.
+    pub fn extend(&self, start: f64, end: f64, min: Point, max: Point) -> Self {
+        let dir = self.to - self.from;
+        let len = self.from.distance(self.to);
+        if len == 0.0 {
+            return self.clone();
+        }
+        // Parameters of the edges of the rectangle, if the line crosses it.
+        let (enter, exit) = clip_range(self.from, dir, min, max).unwrap_or((0.0, 1.0));
+        let start = if start.is_infinite() {
+            enter.min(0.0)
+        } else {
+            -start / len
+        };
+        let end = if end.is_infinite() {
+            exit.max(1.0)
+        } else {
+            1.0 + end / len
+        };
+        Segment {
+            from: self.from + dir * start,
+            to: self.from + dir * end,
+        }
+    }
+}
+
+/// The range of `t` such that `P + t * dir` is inside the rectangle with
+/// corners `min` and `max`, or `None` if it is empty.
+fn clip_range(P: Point, dir: Point, min: Point, max: Point) -> Option<(f64, f64)> {
+    let mut start = f64::NEG_INFINITY;
+    let mut end = f64::INFINITY;
+    for (p, d, lo, hi) in [(P.x, dir.x, min.x, max.x), (P.y, dir.y, min.y, max.y)] {
+        if d == 0.0 {
+            if p < lo || p > hi {
+                return None;
+            }
+        } else {
+            let (t1, t2) = ((lo - p) / d, (hi - p) / d);
+            start = start.max(t1.min(t2));
+            end = end.min(t1.max(t2));
+        }
+    }
+    if start < end {
+        Some((start, end))
+    } else {
+        None
+    }
 }
 
 impl PointOn for Segment {
@@ -95,3 +176,27 @@ impl PointOn for Arc {
         self.from.rotate(self.O, angle)
     }
 }
+
+impl Path {
+    /// A point on the first piece, `0` at its start and `1` at its end, or
+    /// `None` if there is no such piece.
+    pub fn point_on(&self, pos: f64) -> Option<Point> {
+        let piece = self.pieces.first().filter(|piece| piece.len() >= 2)?;
+        let n = if self.closed {
+            piece.len()
+        } else {
+            piece.len() - 1
+        };
+        let t = pos * n as f64;
+        if self.closed {
+            let i = t.floor();
+            let k = (i as i64).rem_euclid(n as i64) as usize;
+            Some(Segment::new(piece[k], piece[(k + 1) % n]).point_on(t - i))
+        } else {
+            // Extended past the ends like a segment.
+            let i = t.floor().clamp(0.0, n as f64 - 1.0);
+            let k = i as usize;
+            Some(Segment::new(piece[k], piece[k + 1]).point_on(t - i))
+        }
+    }
+}
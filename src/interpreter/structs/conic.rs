@@ -0,0 +1,452 @@
+// Conic sections, given by their equations
+// `ax^2 + bxy + cy^2 + dx + ey + f = 0`.
+#![allow(non_snake_case)]
+
+use super::Path;
+use metric_rs::{
+    calc::{
+        basic::Distance,
+        transform::{Reflect, Rotate, Scale},
+    },
+    objects::{Circle, Line, Point},
+};
+#[cfg(test)]
+use serde::Serialize;
+use std::f64::consts::PI;
+use thiserror::Error;
+
+/// Tolerance of the computations on conics.
+const EPSILON: f64 = 1e-9;
+/// Number of segments of each piece of a conic when drawing it.
+const SAMPLES: usize = 256;
+
+#[derive(Debug, Error)]
+pub enum ConicError {
+    #[error("Degenerate conic")]
+    Degenerate,
+    #[error("Empty conic")]
+    Empty,
+    #[error("No intersection")]
+    NoIntersection,
+    #[error("No tangent from a point inside the conic")]
+    NoTangent,
+}
+
+type ConicResult<T> = Result<T, ConicError>;
+
+/// A 3x3 matrix acting on homogeneous coordinates `(x, y, 1)`.
+type Mat3 = [[f64; 3]; 3];
+
+#[cfg_attr(test, derive(Serialize))]
+#[derive(Debug, Clone, Copy)]
+pub struct Conic {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+}
+
+impl Conic {
+    /// Scale the coefficients to a unit vector, with the first nonzero
+    /// coefficient positive, so that equal conics have equal coefficients.
+    fn from_coef(coef: [f64; 6]) -> Self {
+        let norm = coef.iter().map(|x| x * x).sum::<f64>().sqrt();
+        let scale = match coef.iter().find(|x| x.abs() > EPSILON * norm) {
+            Some(x) => x.signum() / norm,
+            None => 1.0,
+        };
+        let [a, b, c, d, e, f] = coef.map(|x| x * scale);
+        Conic { a, b, c, d, e, f }
+    }
+    /// The symmetric matrix `M` such that the conic is `X^T M X = 0`.
+    fn matrix(&self) -> Mat3 {
+        let Conic { a, b, c, d, e, f } = *self;
+        [
+            [a, b / 2.0, d / 2.0],
+            [b / 2.0, c, e / 2.0],
+            [d / 2.0, e / 2.0, f],
+        ]
+    }
+    #[inline]
+    pub fn from_circle(circle: Circle) -> Self {
+        let Circle { O, r } = circle;
+        Self::from_coef([
+            1.0,
+            0.0,
+            1.0,
+            -2.0 * O.x,
+            -2.0 * O.y,
+            O.x * O.x + O.y * O.y - r * r,
+        ])
+    }
+    /// The conic through five points.
+    pub fn from_5p(points: [Point; 5]) -> ConicResult<Self> {
+        // Each point gives a linear equation of the coefficients, so the
+        // coefficients span the null space of this matrix. Solve it by
+        // Gauss-Jordan elimination, with rows scaled to make the tolerance
+        // independent of the coordinates.
+        let mut m = points.map(|Point { x, y }| {
+            let row = [x * x, x * y, y * y, x, y, 1.0];
+            let max = row.iter().fold(0.0_f64, |max, x| max.max(x.abs()));
+            row.map(|x| x / max)
+        });
+        let mut pivots = Vec::with_capacity(5);
+        for col in 0..6 {
+            let row = pivots.len();
+            if row == 5 {
+                break;
+            }
+            let best = (row..5)
+                .max_by(|&i, &j| m[i][col].abs().total_cmp(&m[j][col].abs()))
+                .unwrap();
+            if m[best][col].abs() <= EPSILON {
+                continue;
+            }
+            m.swap(row, best);
+            let pivot = m[row][col];
+            m[row] = m[row].map(|x| x / pivot);
+            let reduced = m[row];
+            for (i, r) in m.iter_mut().enumerate() {
+                if i != row {
+                    let factor = r[col];
+                    for (x, y) in r.iter_mut().zip(reduced) {
+                        *x -= factor * y;
+                    }
+                }
+            }
+            pivots.push(col);
+        }
+        // Otherwise there are infinitely many conics through the points.
+        if pivots.len() < 5 {
+            return Err(ConicError::Degenerate);
+        }
+        let free = (0..6).find(|col| !pivots.contains(col)).unwrap();
+        let mut coef = [0.0; 6];
+        coef[free] = 1.0;
+        for (row, &col) in pivots.iter().enumerate() {
+            coef[col] = -m[row][free];
+        }
+        Ok(Self::from_coef(coef))
+    }
+    /// The conic with focus `F`, directrix `l` and eccentricity `e`.
+    pub fn from_focus_directrix(F: Point, l: Line, e: f64) -> ConicResult<Self> {
+        if e <= 0.0 || F.distance(l) <= EPSILON {
+            return Err(ConicError::Degenerate);
+        }
+        // |XF|^2 = e^2 * d(X, l)^2
+        let Line { a, b, c } = l;
+        let k = e * e / (a * a + b * b);
+        Ok(Self::from_coef([
+            1.0 - k * a * a,
+            -2.0 * k * a * b,
+            1.0 - k * b * b,
+            -2.0 * F.x - 2.0 * k * a * c,
+            -2.0 * F.y - 2.0 * k * b * c,
+            F.x * F.x + F.y * F.y - k * c * c,
+        ]))
+    }
+    /// The ellipse with foci `F` and `G`, passing through `P`.
+    #[inline]
+    pub fn ellipse(F: Point, G: Point, P: Point) -> ConicResult<Self> {
+        Self::from_foci(F, G, P, 1.0)
+    }
+    /// The hyperbola with foci `F` and `G`, passing through `P`.
+    #[inline]
+    pub fn hyperbola(F: Point, G: Point, P: Point) -> ConicResult<Self> {
+        Self::from_foci(F, G, P, -1.0)
+    }
+    /// An ellipse if `sign` is `1` and a hyperbola if `sign` is `-1`.
+    fn from_foci(F: Point, G: Point, P: Point, sign: f64) -> ConicResult<Self> {
+        let M = (F + G) / 2.0;
+        let c = F.distance(G) / 2.0;
+        let a = (P.distance(F) + sign * P.distance(G)).abs() / 2.0;
+        let b2 = sign * (a * a - c * c);
+        if a <= EPSILON || b2 <= EPSILON {
+            return Err(ConicError::Degenerate);
+        }
+        let (sin, cos) = if c > 0.0 {
+            let dir = (G - F) / (2.0 * c);
+            (dir.y, dir.x)
+        } else {
+            (0.0, 1.0)
+        };
+        // `u^2 / a^2 +- v^2 / b^2 = 1` in coordinates along the axes.
+        let local = Self::from_coef([1.0 / (a * a), 0.0, sign / b2, 0.0, 0.0, -1.0]);
+        Ok(local.transform([
+            [cos, sin, -(cos * M.x + sin * M.y)],
+            [-sin, cos, sin * M.x - cos * M.y],
+            [0.0, 0.0, 1.0],
+        ]))
+    }
+    /// The image of the conic under a map, given the matrix of the
+    /// _inverse_ map in homogeneous coordinates.
+    pub fn transform(&self, inverse: Mat3) -> Self {
+        let m = self.matrix();
+        let h = inverse;
+        // H^T M H
+        let mut r = [[0.0; 3]; 3];
+        for (i, row) in r.iter_mut().enumerate() {
+            for (j, x) in row.iter_mut().enumerate() {
+                for k in 0..3 {
+                    for l in 0..3 {
+                        *x += h[k][i] * m[k][l] * h[l][j];
+                    }
+                }
+            }
+        }
+        Self::from_coef([
+            r[0][0],
+            r[0][1] + r[1][0],
+            r[1][1],
+            r[0][2] + r[2][0],
+            r[1][2] + r[2][1],
+            r[2][2],
+        ])
+    }
+    /// The value of the equation at a point.
+    #[inline]
+    fn value(&self, P: Point) -> f64 {
+        let Conic { a, b, c, d, e, f } = *self;
+        let Point { x, y } = P;
+        a * x * x + b * x * y + c * y * y + d * x + e * y + f
+    }
+    /// The polar line of a point.
+    pub fn polar(&self, P: Point) -> ConicResult<Line> {
+        let m = self.matrix();
+        let [a, b, c] = m.map(|row| row[0] * P.x + row[1] * P.y + row[2]);
+        if a.hypot(b) <= EPSILON {
+            Err(ConicError::Degenerate)
+        } else {
+            Ok(Line { a, b, c })
+        }
+    }
+    /// Intersections with a line. The second one is `None` if the line is
+    /// parallel to an asymptote or to the axis of a parabola.
+    pub fn inter(&self, l: Line) -> ConicResult<(Point, Option<Point>)> {
+        let Line { a, b, c } = l;
+        let norm = a.hypot(b);
+        let P = Point {
+            x: -a * c / (norm * norm),
+            y: -b * c / (norm * norm),
+        };
+        let dir = Point {
+            x: b / norm,
+            y: -a / norm,
+        };
+        // The equation restricted to `P + t * dir`.
+        let m = self.matrix();
+        let p = [P.x, P.y, 1.0];
+        let q = [dir.x, dir.y, 0.0];
+        let form = |u: [f64; 3], v: [f64; 3]| {
+            (0..3)
+                .map(|i| (0..3).map(|j| u[i] * m[i][j] * v[j]).sum::<f64>())
+                .sum::<f64>()
+        };
+        let (ta, tb, tc) = (form(q, q), 2.0 * form(p, q), form(p, p));
+        if ta.abs() <= EPSILON {
+            return if tb.abs() <= EPSILON {
+                Err(ConicError::NoIntersection)
+            } else {
+                Ok((P + dir * (-tc / tb), None))
+            };
+        }
+        let disc = tb * tb - 4.0 * ta * tc;
+        if disc < -EPSILON {
+            return Err(ConicError::NoIntersection);
+        }
+        let sqrt = disc.max(0.0).sqrt();
+        let (t1, t2) = ((-tb - sqrt) / (2.0 * ta), (-tb + sqrt) / (2.0 * ta));
+        Ok((P + dir * t1.min(t2), Some(P + dir * t1.max(t2))))
+    }
+    /// Intersections with a line through a common point `P`. The other
+    /// intersection is the first returned value.
+    pub fn inter_common(&self, l: Line, P: Point) -> ConicResult<(Point, Option<Point>)> {
+        match self.inter(l)? {
+            (X, Some(Y)) if X.distance(P) < Y.distance(P) => Ok((Y, Some(X))),
+            result => Ok(result),
+        }
+    }
+    /// Tangent lines through a point. If the point is on the conic, both
+    /// are the tangent line at that point.
+    pub fn tangents(&self, P: Point) -> ConicResult<(Line, Line)> {
+        let polar = self.polar(P)?;
+        if P.distance(*self) <= EPSILON {
+            return Ok((polar, polar));
+        }
+        let tangent = |T: Point| Line::from_2p(P, T).map_err(|_| ConicError::Degenerate);
+        match self.inter(polar) {
+            Ok((T, Some(U))) => Ok((tangent(T)?, tangent(U)?)),
+            Ok((T, None)) => {
+                let l = tangent(T)?;
+                Ok((l, l))
+            }
+            Err(_) => Err(ConicError::NoTangent),
+        }
+    }
+    /// Points on the conic covering its part inside the rectangle with
+    /// corners `min` and `max`.
+    pub fn path(&self, min: Point, max: Point) -> ConicResult<Path> {
+        let Conic { a, b, c, d, e, f } = *self;
+        // Rotate the axes to remove the `xy` term, making the equation
+        // `p u^2 + q v^2 + D u + E v + f = 0`.
+        let mut angle = 0.5 * b.atan2(a - c);
+        let (mut sin, mut cos) = angle.sin_cos();
+        let mut p = a * cos * cos + b * cos * sin + c * sin * sin;
+        let mut q = a * sin * sin - b * sin * cos + c * cos * cos;
+        if p.abs() < q.abs() {
+            // Keep `q` as the smaller one, for parabolas.
+            angle += PI / 2.0;
+            (sin, cos) = angle.sin_cos();
+            (p, q) = (q, p);
+        }
+        let (D, E) = (d * cos + e * sin, -d * sin + e * cos);
+        let point = |u: f64, v: f64| Point {
+            x: u * cos - v * sin,
+            y: u * sin + v * cos,
+        };
+        let corners = [
+            min,
+            max,
+            Point { x: min.x, y: max.y },
+            Point { x: max.x, y: min.y },
+        ];
+        if p.abs() <= EPSILON {
+            return Err(ConicError::Degenerate);
+        }
+        if q.abs() <= EPSILON {
+            // A parabola, `v = -(p u^2 + D u + f) / E`. Every visible point
+            // has `u` between those of the corners.
+            if E.abs() <= EPSILON {
+                return Err(ConicError::Degenerate);
+            }
+            let us = corners.map(|X| X.x * cos + X.y * sin);
+            let (from, to) = (
+                us.iter().copied().fold(f64::INFINITY, f64::min),
+                us.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+            );
+            let piece = (0..=SAMPLES)
+                .map(|i| {
+                    let u = from + (to - from) * i as f64 / SAMPLES as f64;
+                    point(u, -(p * u * u + D * u + f) / E)
+                })
+                .collect();
+            return Ok(Path {
+                pieces: vec![piece],
+                closed: false,
+            });
+        }
+        // `p (u - u0)^2 + q (v - v0)^2 = k`
+        let (u0, v0) = (-D / (2.0 * p), -E / (2.0 * q));
+        let k = p * u0 * u0 + q * v0 * v0 - f;
+        if k.abs() <= EPSILON {
+            return Err(ConicError::Degenerate);
+        }
+        let (ru, rv) = (k / p, k / q);
+        if ru > 0.0 && rv > 0.0 {
+            let (ru, rv) = (ru.sqrt(), rv.sqrt());
+            let piece = (0..SAMPLES)
+                .map(|i| {
+                    let (sin, cos) = (2.0 * PI * i as f64 / SAMPLES as f64).sin_cos();
+                    point(u0 + ru * cos, v0 + rv * sin)
+                })
+                .collect();
+            return Ok(Path {
+                pieces: vec![piece],
+                closed: true,
+            });
+        }
+        if ru < 0.0 && rv < 0.0 {
+            return Err(ConicError::Empty);
+        }
+        // A hyperbola. Its branches are drawn until they are farther from
+        // the center than every corner.
+        let O = point(u0, v0);
+        let far = corners.iter().map(|X| X.distance(O)).fold(0.0, f64::max);
+        // Whether the transverse axis is along `u`.
+        let along_u = ru > 0.0;
+        let (ru, rv) = (ru.abs().sqrt(), rv.abs().sqrt());
+        let (ra, rb) = if along_u { (ru, rv) } else { (rv, ru) };
+        let range = (far / ra).max(1.0).acosh().min((far / rb).asinh());
+        let pieces = [1.0, -1.0]
+            .iter()
+            .map(|branch| {
+                (0..=SAMPLES)
+                    .map(|i| {
+                        let s = range * (2.0 * i as f64 / SAMPLES as f64 - 1.0);
+                        if along_u {
+                            point(u0 + branch * ru * s.cosh(), v0 + rv * s.sinh())
+                        } else {
+                            point(u0 + ru * s.sinh(), v0 + branch * rv * s.cosh())
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(Path {
+            pieces,
+            closed: false,
+        })
+    }
+}
+
+/// Distance to a conic, approximated to the first order, which is accurate
+/// for points near the conic.
+impl Distance<Conic> for Point {
+    #[inline]
+    fn distance(&self, conic: Conic) -> f64 {
+        let Conic { a, b, c, d, e, .. } = conic;
+        let gradient = (2.0 * a * self.x + b * self.y + d).hypot(b * self.x + 2.0 * c * self.y + e);
+        let value = conic.value(*self).abs();
+        if gradient <= EPSILON {
+            value
+        } else {
+            value / gradient
+        }
+    }
+}
+
+impl Rotate for Conic {
+    #[inline]
+    fn rotate(&self, O: Point, t: f64) -> Self {
+        let (sin, cos) = (-t).sin_cos();
+        self.transform([
+            [cos, -sin, O.x - cos * O.x + sin * O.y],
+            [sin, cos, O.y - sin * O.x - cos * O.y],
+            [0.0, 0.0, 1.0],
+        ])
+    }
+}
+
+impl Scale for Conic {
+    #[inline]
+    fn scale(&self, O: Point, k: f64) -> Self {
+        let k = 1.0 / k;
+        self.transform([
+            [k, 0.0, O.x * (1.0 - k)],
+            [0.0, k, O.y * (1.0 - k)],
+            [0.0, 0.0, 1.0],
+        ])
+    }
+}
+
+impl Reflect<Point> for Conic {
+    #[inline]
+    fn reflect_in(&self, O: Point) -> Self {
+        self.scale(O, -1.0)
+    }
+}
+
+impl Reflect<Line> for Conic {
+    #[inline]
+    fn reflect_in(&self, l: Line) -> Self {
+        let Line { a, b, c } = l;
+        let n = a * a + b * b;
+        self.transform([
+            [1.0 - 2.0 * a * a / n, -2.0 * a * b / n, -2.0 * a * c / n],
+            [-2.0 * a * b / n, 1.0 - 2.0 * b * b / n, -2.0 * b * c / n],
+            [0.0, 0.0, 1.0],
+        ])
+    }
+}
@@ -0,0 +1,251 @@
+// Projective maps of the plane, which can be composed and applied to objects.
+#![allow(non_snake_case)]
+
+use super::conic::Conic;
+use metric_rs::objects::{Circle, Line, Point};
+#[cfg(test)]
+use serde::Serialize;
+use thiserror::Error;
+
+/// Tolerance of the computations on transformations.
+const EPSILON: f64 = 1e-9;
+
+#[derive(Debug, Error)]
+pub enum TransformError {
+    #[error("Degenerate transformation")]
+    Degenerate,
+    #[error("The transformation is not invertible")]
+    Singular,
+    #[error("The image is at infinity")]
+    Infinite,
+    #[error("Rays can only be mapped by affine transformations")]
+    NotAffine,
+}
+
+type TransformResult<T> = Result<T, TransformError>;
+
+/// A 3x3 matrix acting on homogeneous coordinates `(x, y, 1)`.
+type Mat3 = [[f64; 3]; 3];
+
+/// A projective map, given by its matrix in homogeneous coordinates. Affine
+/// maps have `(0 0 1)` as the last row.
+#[cfg_attr(test, derive(Serialize))]
+#[derive(Debug, Clone, Copy)]
+pub struct Transform {
+    pub m: Mat3,
+}
+
+/// The image of a circle, which is an ellipse unless the map is a similarity.
+pub enum CircleImage {
+    Circle(Circle),
+    Conic(Conic),
+}
+
+#[inline]
+fn product(p: Mat3, q: Mat3) -> Mat3 {
+    let mut r = [[0.0; 3]; 3];
+    for (i, row) in r.iter_mut().enumerate() {
+        for (j, x) in row.iter_mut().enumerate() {
+            *x = (0..3).map(|k| p[i][k] * q[k][j]).sum();
+        }
+    }
+    r
+}
+
+#[inline]
+fn det(m: Mat3) -> f64 {
+    m[0][0] * (m[1][1] * m[2][2] - m[1][2] * m[2][1])
+        - m[0][1] * (m[1][0] * m[2][2] - m[1][2] * m[2][0])
+        + m[0][2] * (m[1][0] * m[2][1] - m[1][1] * m[2][0])
+}
+
+/// The inverse of a matrix, up to a scalar factor.
+fn adjugate(m: Mat3) -> Mat3 {
+    let mut r = [[0.0; 3]; 3];
+    for (i, row) in r.iter_mut().enumerate() {
+        for (j, x) in row.iter_mut().enumerate() {
+            // The cofactor of `m[j][i]`.
+            let (j1, j2) = ((j + 1) % 3, (j + 2) % 3);
+            let (i1, i2) = ((i + 1) % 3, (i + 2) % 3);
+            *x = m[j1][i1] * m[j2][i2] - m[j1][i2] * m[j2][i1];
+        }
+    }
+    r
+}
+
+/// The matrix taking `(1, 0, 0)`, `(0, 1, 0)`, `(0, 0, 1)` and `(1, 1, 1)` in
+/// homogeneous coordinates to the four given points.
+fn from_basis([A, B, C, D]: [Point; 4]) -> TransformResult<Mat3> {
+    let m = [[A.x, B.x, C.x], [A.y, B.y, C.y], [1.0, 1.0, 1.0]];
+    let d = det(m);
+    if d.abs() <= EPSILON {
+        return Err(TransformError::Degenerate);
+    }
+    // Scale the columns so that they add up to `D`.
+    let adj = adjugate(m);
+    let k = [0, 1, 2].map(|i| (adj[i][0] * D.x + adj[i][1] * D.y + adj[i][2]) / d);
+    if k.iter().any(|k| k.abs() <= EPSILON) {
+        return Err(TransformError::Degenerate);
+    }
+    Ok([0, 1, 2].map(|i| [0, 1, 2].map(|j| m[i][j] * k[j])))
+}
+
+impl Transform {
+    /// Scale the matrix, so that equal maps have equal matrices.
+    fn from_matrix(m: Mat3) -> Self {
+        let scale = if m[2][2].abs() > EPSILON {
+            m[2][2]
+        } else {
+            m.iter()
+                .flatten()
+                .copied()
+                .fold(0.0, |x: f64, y| if y.abs() > x.abs() { y } else { x })
+        };
+        Transform {
+            m: m.map(|row| row.map(|x| x / scale)),
+        }
+    }
+    /// The affine map with linear part `(a, b; d, e)` fixing `O`.
+    #[inline]
+    fn fixing(O: Point, [a, b, d, e]: [f64; 4]) -> Self {
+        Transform {
+            m: [
+                [a, b, O.x - a * O.x - b * O.y],
+                [d, e, O.y - d * O.x - e * O.y],
+                [0.0, 0.0, 1.0],
+            ],
+        }
+    }
+    #[inline]
+    pub fn translation(v: Point) -> Self {
+        Transform {
+            m: [[1.0, 0.0, v.x], [0.0, 1.0, v.y], [0.0, 0.0, 1.0]],
+        }
+    }
+    /// The spiral similarity with center `O`, ratio `k` and angle `t`.
+    #[inline]
+    pub fn spiral(O: Point, k: f64, t: f64) -> Self {
+        let (sin, cos) = t.sin_cos();
+        Self::fixing(O, [k * cos, -k * sin, k * sin, k * cos])
+    }
+    #[inline]
+    pub fn rotation(O: Point, t: f64) -> Self {
+        Self::spiral(O, 1.0, t)
+    }
+    #[inline]
+    pub fn scaling(O: Point, k: f64) -> Self {
+        Self::spiral(O, k, 0.0)
+    }
+    pub fn reflection(l: Line) -> TransformResult<Self> {
+        let Line { a, b, c } = l;
+        let n = a * a + b * b;
+        if n <= EPSILON {
+            return Err(TransformError::Degenerate);
+        }
+        Ok(Transform {
+            m: [
+                [1.0 - 2.0 * a * a / n, -2.0 * a * b / n, -2.0 * a * c / n],
+                [-2.0 * a * b / n, 1.0 - 2.0 * b * b / n, -2.0 * b * c / n],
+                [0.0, 0.0, 1.0],
+            ],
+        })
+    }
+    /// The spiral similarity taking `A` to `C` and `B` to `D`.
+    pub fn spiral_2p(A: Point, B: Point, C: Point, D: Point) -> TransformResult<Self> {
+        // `z -> mz + n` in complex numbers, with `m = (D - C) / (B - A)`.
+        let (u, v) = (B - A, D - C);
+        let n = u.x * u.x + u.y * u.y;
+        if n <= EPSILON {
+            return Err(TransformError::Degenerate);
+        }
+        let (p, q) = ((v.x * u.x + v.y * u.y) / n, (v.y * u.x - v.x * u.y) / n);
+        Ok(Self::translation(C - A).then(Self::fixing(C, [p, -q, q, p])))
+    }
+    /// The affine map taking each point of `from` to the point of `to` at
+    /// the same position.
+    pub fn affine(from: [Point; 3], to: [Point; 3]) -> TransformResult<Self> {
+        let [A, B, C] = from;
+        let [P, Q, R] = to;
+        let (u, v) = (B - A, C - A);
+        let (s, t) = (Q - P, R - P);
+        let det = u.x * v.y - v.x * u.y;
+        if det.abs() <= EPSILON {
+            return Err(TransformError::Degenerate);
+        }
+        // The linear part is `(s t) (u v)^-1`.
+        let linear = [
+            (s.x * v.y - t.x * u.y) / det,
+            (t.x * u.x - s.x * v.x) / det,
+            (s.y * v.y - t.y * u.y) / det,
+            (t.y * u.x - s.y * v.x) / det,
+        ];
+        Ok(Self::translation(P - A).then(Self::fixing(P, linear)))
+    }
+    /// The projective map taking each point of `from` to the point of `to`
+    /// at the same position. No three points of either should be collinear.
+    pub fn homography(from: [Point; 4], to: [Point; 4]) -> TransformResult<Self> {
+        let (p, q) = (from_basis(from)?, from_basis(to)?);
+        Ok(Self::from_matrix(product(q, adjugate(p))))
+    }
+    /// The map applying `self`, then `other`.
+    #[inline]
+    pub fn then(self, other: Self) -> Self {
+        Self::from_matrix(product(other.m, self.m))
+    }
+    #[inline]
+    fn is_affine(&self) -> bool {
+        self.m[2][0].abs() <= EPSILON && self.m[2][1].abs() <= EPSILON
+    }
+    pub fn point(&self, P: Point) -> TransformResult<Point> {
+        let [x, y, w] = self.m.map(|row| row[0] * P.x + row[1] * P.y + row[2]);
+        if w.abs() <= EPSILON {
+            return Err(TransformError::Infinite);
+        }
+        Ok(Point { x: x / w, y: y / w })
+    }
+    /// The matrix of the inverse map, up to a scalar factor.
+    #[inline]
+    fn inverse(&self) -> TransformResult<Mat3> {
+        if det(self.m).abs() <= EPSILON {
+            return Err(TransformError::Singular);
+        }
+        Ok(adjugate(self.m))
+    }
+    pub fn line(&self, l: Line) -> TransformResult<Line> {
+        let h = self.inverse()?;
+        let Line { a, b, c } = l;
+        // The line is the row vector `(a b c)`, so its image is `(a b c) H`.
+        let [a, b, c] = [0, 1, 2].map(|j| a * h[0][j] + b * h[1][j] + c * h[2][j]);
+        if a.hypot(b) <= EPSILON * c.abs() {
+            return Err(TransformError::Infinite);
+        }
+        Ok(Line { a, b, c })
+    }
+    pub fn ray(&self, (A, B): (Point, Point)) -> TransformResult<(Point, Point)> {
+        if !self.is_affine() {
+            return Err(TransformError::NotAffine);
+        }
+        Ok((self.point(A)?, self.point(B)?))
+    }
+    pub fn circle(&self, circle: Circle) -> TransformResult<CircleImage> {
+        let [[a, b, _], [d, e, _], _] = self.m;
+        let direct = (a - e).abs() <= EPSILON && (b + d).abs() <= EPSILON;
+        let opposite = (a + e).abs() <= EPSILON && (b - d).abs() <= EPSILON;
+        if self.is_affine() && (direct || opposite) {
+            let k = (a * e - b * d).abs().sqrt();
+            if k <= EPSILON {
+                return Err(TransformError::Singular);
+            }
+            Ok(CircleImage::Circle(Circle {
+                O: self.point(circle.O)?,
+                r: circle.r * k,
+            }))
+        } else {
+            Ok(CircleImage::Conic(self.conic(Conic::from_circle(circle))?))
+        }
+    }
+    #[inline]
+    pub fn conic(&self, conic: Conic) -> TransformResult<Conic> {
+        Ok(conic.transform(self.inverse()?))
+    }
+}
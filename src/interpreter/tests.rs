@@ -1,2 +1,4 @@
+mod diagnostic;
+mod eval;
 mod interpreter;
 mod parser;
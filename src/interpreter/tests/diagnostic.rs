@@ -0,0 +1,91 @@
+use crate::interpreter::interpret::InterpreterState;
+
+/// Interpret `source`, which should fail, and check its error message.
+fn check(name: &str, source: &str) {
+    let mut interpreter = InterpreterState::new();
+    let error = interpreter.interpret(source).unwrap_err();
+    insta::assert_snapshot!(name, error.to_string());
+}
+
+#[test]
+fn test_diagnostic() {
+    check("undefined_name", "A = (0, 0);\nB = mid A, c;\n");
+    check(
+        "draw_number",
+        "A = (0, 0);\nB = (1, 0);\nx = 2;\ndraw A, x[color=\"red\"];\n",
+    );
+    check(
+        "wrong_arguments",
+        "A = (0, 0);\nB = (1, 0);\nP = proj AB, A;\n",
+    );
+}
+
+#[test]
+fn test_argument_names() {
+    check("tan_number", "A = (0, 0);\nl, _ = tan A, 2;\n");
+}
+
+#[test]
+fn test_arithmetic() {
+    check("point_product", "A = (0, 0);\nx = $ A * (A - 1) $;\n");
+}
+
+#[test]
+fn test_macro() {
+    check(
+        "macro_failure",
+        "def f(A) -> B {\n    B = mid A, c;\n}\nA = (0, 0);\nB = f A;\n",
+    );
+}
+
+#[test]
+fn test_include() {
+    check(
+        "include_broken",
+        "include \"test_input/include/broken.prs\";\n",
+    );
+    check(
+        "include_cycle",
+        "include \"test_input/include/cycle.prs\";\n",
+    );
+}
+
+#[test]
+fn test_loop() {
+    check(
+        "loop_missing_index",
+        "A = (0, 0);\nfor k in 0..3 {\n    P{k} = mid A, P{k - 1};\n}\n",
+    );
+    check("eval_stray_brace", "k = 1;\nx = $ {k} + 1 $;\n");
+}
+
+#[test]
+fn test_assert() {
+    check(
+        "assert_collinear",
+        "A = (0, 0);\nB = (1, 0);\nC = (2, 1);\nassert collinear A, B, C;\n",
+    );
+    check(
+        "assert_collinear_small",
+        "A = (0, 0);\nB = (0.001, 0);\nC = (0.002, 0.0005);\nassert collinear A, B, C;\n",
+    );
+    check(
+        "assert_concurrent_small",
+        "A = (0, 0);\nB = (0.001, 0);\nC = (0, 0.001);\nD = (0.001, 0.0005);\nassert concurrent AB, AC, CD;\n",
+    );
+    check(
+        "assert_concurrent_parallel",
+        "A = (0, 0);\nB = (1, 0);\nC = (0, 1);\nD = (1, 1);\nE = (0, 2);\nF = (1, 2);\nassert concurrent AB, CD, EF;\n",
+    );
+}
+
+#[test]
+fn test_recover() {
+    let mut interpreter = InterpreterState::new();
+    interpreter.set_recover(true);
+    let error = interpreter
+        .interpret("A = (0, 0);\nB = mid A, c;\nC = mid A, B;\ndraw A, B, C;\n")
+        .unwrap_err();
+    insta::assert_snapshot!(error.to_string());
+    assert!(interpreter.emit().unwrap().contains("<circle"));
+}
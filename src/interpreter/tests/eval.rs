@@ -0,0 +1,130 @@
+use crate::interpreter::{
+    eval::EvalError,
+    interpret::{InterpretError, InterpreterState},
+    utils::GObject,
+};
+use metric_rs::objects::Point;
+
+/// Evaluate `expr` after interpreting `source`, expecting a number.
+fn number(source: &str, expr: &str) -> f64 {
+    let mut interpreter = InterpreterState::new();
+    interpreter.interpret(source).unwrap();
+    match interpreter.eval(expr).unwrap() {
+        GObject::Number(x) => x,
+        obj => panic!("expected a number, found {}", obj.kind()),
+    }
+}
+
+/// Evaluate `expr` after interpreting `source`, expecting an error.
+fn error(source: &str, expr: &str) -> anyhow::Error {
+    let mut interpreter = InterpreterState::new();
+    interpreter.interpret(source).unwrap();
+    interpreter.eval(expr).unwrap_err()
+}
+
+#[test]
+fn test_precedence() {
+    assert_eq!(number("", "1 + 2 * 3"), 7.0);
+    assert_eq!(number("", "(1 + 2) * 3"), 9.0);
+    assert_eq!(number("", "1 - 2 - 3"), -4.0);
+    assert_eq!(number("", "8 / 2 / 2"), 2.0);
+    assert_eq!(number("", "2 * 3 ^ 2"), 18.0);
+    assert_eq!(number("A = (3, 4);", "2 * A.y ^ 2"), 32.0);
+    assert_eq!(number("A = (3, 4);", "|A| + 1"), 6.0);
+}
+
+#[test]
+fn test_unary_minus() {
+    assert_eq!(number("", "-2 ^ 2"), -4.0);
+    assert_eq!(number("", "2 ^ -1"), 0.5);
+    assert_eq!(number("", "--3"), 3.0);
+    assert_eq!(number("", "2 * -3"), -6.0);
+    assert_eq!(number("A = (3, 4);", "(-A).x"), -3.0);
+}
+
+#[test]
+fn test_power() {
+    assert_eq!(number("", "2 ^ 3 ^ 2"), 512.0);
+    assert_eq!(number("", "(2 ^ 3) ^ 2"), 64.0);
+}
+
+#[test]
+fn test_numbers() {
+    assert_eq!(number("", "1e-3"), 1e-3);
+    assert_eq!(number("", "2.5E2 + 1e+1"), 260.0);
+    assert_eq!(number("", "1e2deg"), 100f64.to_radians());
+    assert_eq!(number("", "2 * e"), 2.0 * std::f64::consts::E);
+}
+
+#[test]
+fn test_constants() {
+    assert_eq!(number("", "pi"), std::f64::consts::PI);
+    assert_eq!(number("", "e"), std::f64::consts::E);
+    assert_eq!(number("", "180 * deg"), std::f64::consts::PI);
+    assert_eq!(number("", "90deg"), std::f64::consts::FRAC_PI_2);
+    assert_eq!(number("e = 3;", "e * 2"), 6.0);
+    assert_eq!(number("pi = (1, 2);", "pi.y"), 2.0);
+}
+
+#[test]
+fn test_points() {
+    let mut interpreter = InterpreterState::new();
+    interpreter.interpret("A = (1, 2); B = (3, 6);").unwrap();
+    match interpreter.eval("A + 0.5 * (B - A)").unwrap() {
+        GObject::Point(Point { x, y }) => assert_eq!((x, y), (2.0, 4.0)),
+        obj => panic!("expected a point, found {}", obj.kind()),
+    }
+}
+
+#[test]
+fn test_errors() {
+    let eval_error = |source, expr| error(source, expr).downcast::<EvalError>().unwrap();
+    assert!(matches!(eval_error("", "1 +"), EvalError::UnexpectedEnd));
+    assert!(matches!(
+        eval_error("", "1 2"),
+        EvalError::UnexpectedToken(_)
+    ));
+    assert!(matches!(
+        eval_error("", "2e"),
+        EvalError::UnexpectedToken(_)
+    ));
+    assert!(matches!(eval_error("", "(1, 2"), EvalError::UnexpectedEnd));
+    assert!(matches!(
+        eval_error("", "1 # 2"),
+        EvalError::UnexpectedChar('#')
+    ));
+    assert!(matches!(
+        eval_error("", "foo(1)"),
+        EvalError::UnknownFunction(_)
+    ));
+    assert!(matches!(
+        eval_error("", "sqrt(1, 2)"),
+        EvalError::ArgCount(_, 1, 2)
+    ));
+    assert!(matches!(eval_error("", "min()"), EvalError::NoArgs(_)));
+    assert!(matches!(
+        eval_error("A = (1, 2);", "A * A"),
+        EvalError::Operands('*', _, _)
+    ));
+    assert!(matches!(
+        eval_error("A = (1, 2);", "A.z"),
+        EvalError::NoField(_)
+    ));
+    assert!(matches!(
+        eval_error("A = (1, 2);", "sin(A)"),
+        EvalError::Operand(_, _)
+    ));
+    let interpret_error = |source, expr| error(source, expr).downcast::<InterpretError>().unwrap();
+    assert!(matches!(
+        interpret_error("", "x + 1"),
+        InterpretError::MissingKey(_)
+    ));
+    assert!(matches!(
+        interpret_error("A = (0, 0); B = (1, 0);", "AB"),
+        InterpretError::MissingKey(_)
+    ));
+    assert!(matches!(
+        interpret_error("A = (0, 0); c = @(A, 1);", "c * 2"),
+        InterpretError::WrongGeometricType(_, "Circle")
+    ));
+}
@@ -4,10 +4,39 @@ use crate::interpreter::interpret::InterpreterState;
 fn test_interpreter() {
     insta::with_settings!({sort_maps => true}, {
         insta::glob!("../../../test_input", "*.prs", |path| {
-            let input = std::fs::read_to_string(path).unwrap();
             let mut interpreter = InterpreterState::new();
-            interpreter.interpret(&input).unwrap();
+            interpreter.interpret_file(path).unwrap();
             insta::assert_ron_snapshot!(interpreter);
         })
     })
 }
+
+#[test]
+fn test_rebind_redraw() {
+    let mut interpreter = InterpreterState::new();
+    interpreter
+        .interpret("A = (0, 0); B = (2, 0); M = mid A, B; draw M, A[label=\"A\"];")
+        .unwrap();
+    interpreter.interpret("A = (2, 2);").unwrap();
+    let svg = interpreter.emit().unwrap();
+    // M is now (2, 1), and A is drawn at its new place.
+    assert!(svg.contains("cx=\"2cm\" cy=\"-1cm\""));
+    assert!(svg.contains("cx=\"2cm\" cy=\"-2cm\""));
+    assert!(!svg.contains("cx=\"0cm\""));
+    assert!(!svg.contains("cx=\"1cm\""));
+    assert!(svg.contains("y=\"-2cm\">A</text>"));
+}
+
+#[test]
+fn test_rebind_kind() {
+    let mut interpreter = InterpreterState::new();
+    interpreter
+        .interpret("A = (0, 0); B = (2, 0); M = mid A, B; draw M;")
+        .unwrap();
+    // A number cannot take the place of a point, so it shadows it.
+    interpreter.interpret("A = 3; C = (1, 1);").unwrap();
+    assert!(interpreter
+        .emit()
+        .unwrap()
+        .contains("cx=\"1cm\" cy=\"-0cm\""));
+}
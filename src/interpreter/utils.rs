@@ -1,6 +1,11 @@
 use super::{
     interpret::InterpretError,
-    structs::{Arc, Segment},
+    structs::{
+        conic::Conic,
+        conic::ConicError,
+        transform::{Transform, TransformError},
+        Arc, Path, Segment,
+    },
 };
 use anyhow::Result;
 use metric_rs::{
@@ -8,7 +13,7 @@ use metric_rs::{
 };
 #[cfg(test)]
 use serde::Serialize;
-use std::fmt::Display;
+use std::{collections::HashMap, fmt::Display};
 use thiserror::Error;
 
 #[cfg_attr(test, derive(Serialize))]
@@ -66,12 +71,37 @@ impl From<&str> for ConfigValue {
 pub enum GObject {
     Point(Point),
     Line(Line),
+    /// A ray from the first point, through the second one.
+    Ray((Point, Point)),
     Circle(Circle),
+    Conic(Conic),
     Trig((Point, Point, Point)),
+    Transform(Transform),
     Number(f64),
     None,
 }
 
+impl GObject {
+    /// Name of the type of this object, as shown in error messages.
+    #[inline]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Point(_) => "Point",
+            Self::Line(_) => "Line",
+            Self::Ray(_) => "Ray",
+            Self::Circle(_) => "Circle",
+            Self::Conic(_) => "Conic",
+            Self::Trig(_) => "Trig",
+            Self::Transform(_) => "Transform",
+            Self::Number(_) => "Number",
+            Self::None => "None",
+        }
+    }
+}
+
+/// Keyword arguments `name=value` passed to a method.
+pub type Keywords = HashMap<String, GObject>;
+
 /// Objects related to drawing.
 #[derive(Debug)]
 pub enum DObject {
@@ -79,6 +109,7 @@ pub enum DObject {
     Arc(Arc),
     Point(Point),
     Circle(Circle),
+    Path(Path),
     Polygon(Vec<Point>),
     Angle3P(Point, Point, Point),
 }
@@ -89,7 +120,10 @@ impl From<GObject> for Result<DObject> {
         match val {
             GObject::Circle(c) => Ok(DObject::Circle(c)),
             GObject::Point(p) => Ok(DObject::Point(p)),
-            _ => Err(InterpretError::WrongGeometricType)?,
+            obj => Err(InterpretError::WrongGeometricType(
+                "Point, Line, Ray, Circle or Conic",
+                obj.kind(),
+            ))?,
         }
     }
 }
@@ -110,10 +144,48 @@ pub enum DecorError {
 
 #[derive(Debug, Error)]
 pub enum FuncError {
-    #[error("Argument error")]
-    ArgError,
+    /// Name of the method, kinds of the arguments received and the argument
+    /// kinds of every accepted overload.
+    #[error(
+        "Wrong arguments for {0}: received ({}), accepted {}",
+        .1.join(", "),
+        .2.iter().map(|kinds| format!("({})", kinds.join(", "))).collect::<Vec<_>>().join(" or ")
+    )]
+    ArgError(
+        String,
+        Vec<&'static str>,
+        &'static [&'static [&'static str]],
+    ),
     #[error("No such method: {0}")]
     NoFunc(String),
+    #[error("{0} takes no keyword argument {1}")]
+    NoKeyword(String, String),
+    /// Name of the method and of the keyword, and the expected and received
+    /// kinds.
+    #[error("Wrong keyword argument {1} for {0}: expected {2}, found {3}")]
+    KeywordError(String, String, &'static str, &'static str),
+    #[error("No such triangle center: X({0})")]
+    NoCenter(f64),
+    #[error("Triangle center X({0}) is not defined for this triangle")]
+    UndefinedCenter(f64),
+    #[error("The polar circle only exists for obtuse triangles")]
+    NoPolarCircle,
+    #[error("No intersection on the ray")]
+    OffRay,
+    #[error("No intersection other than the excepted point")]
+    NoOther,
+    #[error("No circle is tangent to all three objects")]
+    NoApollonius,
+    #[error("No solution {0}: only {1} found")]
+    NoSolution(f64, usize),
+    #[error("Points are neither collinear nor concyclic")]
+    NotConcyclic,
+    #[error("The point is at infinity")]
+    AtInfinity,
+    #[error("{0}")]
+    ConicError(#[from] ConicError),
+    #[error("{0}")]
+    TransformError(#[from] TransformError),
     #[error("Calculation exception: {0}")]
     CalcError(CalcException)
 }
@@ -126,10 +198,3 @@ impl From<bool> for ConfigValue {
         Self::Bool(value)
     }
 }
-
-impl From<meval::Error> for InterpretError {
-    #[inline]
-    fn from(value: meval::Error) -> Self {
-        Self::EvalError(value)
-    }
-}